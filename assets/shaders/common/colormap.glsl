@@ -0,0 +1,8 @@
+// Four evenly spaced colour stops, from no pheromone to the peak.
+uniform vec3 colormap[4];
+
+vec3 colorAt(float t) {
+    t = clamp(t, 0.0, 1.0) * 3.0;
+    int i = min(int(t), 2);
+    return mix(colormap[i], colormap[i + 1], t - float(i));
+}
@@ -0,0 +1,24 @@
+#ifndef FRAME_GLSL
+#define FRAME_GLSL
+
+// Per-frame data shared by every program, FrameBlock in
+// src/shader/uniform_block.rs.
+
+#define MAX_LIGHTS 4
+
+// In camera space.
+struct Light {
+    vec4 position; // w 1, or with w 0 the direction the light shines in
+    vec4 color;
+};
+
+layout(std140) uniform Frame {
+    mat4 P;
+    mat4 V;
+    vec4 cameraPosition; // world space
+    float time;          // simulated seconds
+    int lightCount;
+    Light lights[MAX_LIGHTS];
+};
+
+#endif
@@ -0,0 +1,22 @@
+#include "frame.glsl"
+#include "material.glsl"
+
+// Diffuse and specular light from one light, with the viewer at the origin
+// of camera space.
+vec3 shade(Light light, vec3 norm, vec3 fragPos, vec3 viewDir, vec3 baseColor) {
+    vec3 lightDir = light.position.w == 0.0 ? normalize(-light.position.xyz) : normalize(light.position.xyz - fragPos);
+    float diff = max(dot(norm, lightDir), 0.0);
+    vec3 reflectDir = reflect(-lightDir, norm);
+    float spec = pow(max(dot(viewDir, reflectDir), 0.0), shininess);
+    return light.color.rgb * (diff * baseColor + specular * spec);
+}
+
+// Ambient plus every light of the frame on a surface at fragPos.
+vec3 lighting(vec3 norm, vec3 fragPos, vec3 baseColor) {
+    vec3 viewDir = normalize(-fragPos);
+    vec3 result = ambient * baseColor;
+    for (int i = 0; i < lightCount; i++) {
+        result += shade(lights[i], norm, fragPos, viewDir, baseColor);
+    }
+    return result;
+}
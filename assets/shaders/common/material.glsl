@@ -0,0 +1,11 @@
+#ifndef MATERIAL_GLSL
+#define MATERIAL_GLSL
+
+// How a surface reflects light, MaterialBlock in src/shader/uniform_block.rs.
+layout(std140) uniform Material {
+    float ambient;   // fraction of the base colour shown unlit
+    float specular;  // strength of highlights
+    float shininess; // specular exponent
+};
+
+#endif
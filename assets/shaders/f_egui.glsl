@@ -0,0 +1,14 @@
+#version 330
+
+// Textures and vertex colours are both premultiplied and in gamma space, and
+// are blended as such, which is what egui expects of a non-sRGB framebuffer.
+uniform sampler2D tex;
+
+in vec2 i_tc;
+in vec4 i_color;
+
+out vec4 pixelColor;
+
+void main(void) {
+    pixelColor = i_color * texture(tex, i_tc);
+}
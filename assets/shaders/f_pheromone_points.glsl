@@ -0,0 +1,16 @@
+#version 330
+
+#include "common/colormap.glsl"
+
+in float i_value;
+
+out vec4 pixelColor;
+
+void main(void) {
+    // Round points.
+    vec2 d = gl_PointCoord * 2.0 - 1.0;
+    if (dot(d, d) > 1.0) {
+        discard;
+    }
+    pixelColor = vec4(colorAt(i_value), 0.3 + 0.7 * i_value);
+}
@@ -0,0 +1,17 @@
+#version 330
+
+uniform sampler2D slice; // concentration as a fraction of the peak
+
+#include "common/colormap.glsl"
+
+in vec2 i_tc;
+
+out vec4 pixelColor;
+
+void main(void) {
+    float t = texture(slice, i_tc).r;
+    if (t <= 0.002) {
+        discard;
+    }
+    pixelColor = vec4(colorAt(t), 0.25 + 0.6 * t);
+}
@@ -0,0 +1,14 @@
+#version 330
+
+uniform vec4 color;
+
+in vec3 i_normal;
+
+out vec4 pixelColor;
+
+void main(void) {
+    // Lit from both sides so the inside of the surface shows too.
+    vec3 lightDir = normalize(vec3(5.0, 5.0, 0.0));
+    float nl = abs(dot(normalize(i_normal), lightDir));
+    pixelColor = vec4(color.rgb * (0.3 + 0.7 * nl), color.a);
+}
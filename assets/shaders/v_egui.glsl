@@ -0,0 +1,16 @@
+#version 330
+
+uniform vec2 screenSize; // in points
+
+layout(location = 0) in vec2 position; // in points, from the top left
+layout(location = 1) in vec2 texCoord;
+layout(location = 2) in vec4 color;    // premultiplied, gamma space
+
+out vec2 i_tc;
+out vec4 i_color;
+
+void main(void) {
+    gl_Position = vec4(2.0 * position.x / screenSize.x - 1.0, 1.0 - 2.0 * position.y / screenSize.y, 0.0, 1.0);
+    i_tc = texCoord;
+    i_color = color;
+}
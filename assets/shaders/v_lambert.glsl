@@ -1,31 +1,31 @@
 #version 330
 
-// Zmienne jednorodne
-uniform mat4 P;
-uniform mat4 V;
-uniform mat4 M;
-
-
-uniform vec4 color=vec4(1,1,1,1);
+// With INSTANCED, the model matrix and colour are taken per instance.
+
+#include "common/frame.glsl"
 
 //Atrybuty
 layout (location=0) in vec4 vertex; //wspolrzedne wierzcholka w przestrzeni modelu
 layout (location=1) in vec4 normal; //wektor normalny w wierzcholku
-
-
-//Zmienne interpolowane
-out vec4 i_color;
-
-void main(void) {
-    gl_Position=P*V*M*vertex;
-
-    mat4 G=mat4(inverse(transpose(mat3(M))));
-    vec4 n=normalize(G*normal);
-
-	vec4 lightDir=normalize(vec4(5.0,5.0,0.0,0));
-    float nl=clamp(dot(n,lightDir),0,1);
-
-    i_color=vec4(color.rgb*nl,color.a);
-}
+#ifdef INSTANCED
+layout (location=3) in mat4 M;     // locations 3-6
+layout (location=7) in vec4 color;
+#else
+uniform mat4 M;
+uniform vec4 color=vec4(1,1,1,1);
+#endif
 
+//Zmienne interpolowane
+out vec4 i_color;
 
+void main(void) {
+    gl_Position=P*V*M*vertex;
+
+    mat4 G=mat4(inverse(transpose(mat3(M))));
+    vec4 n=normalize(G*normal);
+
+	vec4 lightDir=normalize(vec4(5.0,5.0,0.0,0));
+    float nl=clamp(dot(n,lightDir),0,1);
+
+    i_color=vec4(color.rgb*nl,color.a);
+}
@@ -1,18 +1,20 @@
 #version 330
 
-//Zmienne jednorodne
-uniform mat4 P;
-uniform mat4 V;
-uniform mat4 M;
+// With INSTANCED, the model matrix is taken per instance.
 
+#include "common/frame.glsl"
 
 uniform vec4 lightDir=vec4(5.0,0,0,0);
 
 //Atrybuty
 layout (location=0) in vec4 vertex; //wspolrzedne wierzcholka w przestrzeni modelu
 layout (location=1) in vec4 normal; //wektor normalny w wierzcholku
-layout (location=2) in vec2 texCoord; //wsprzdne teksturowania
-
+layout (location=2) in vec2 texCoord; //wspolrzedne teksturowania
+#ifdef INSTANCED
+layout (location=3) in mat4 M;     // locations 3-6
+#else
+uniform mat4 M;
+#endif
 
 //Zmienne interpolowane
 out vec2 i_tc;
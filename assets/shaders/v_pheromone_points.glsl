@@ -0,0 +1,15 @@
+#version 330
+
+#include "common/frame.glsl"
+uniform float pointSize; // pixels across a point one unit in front of the camera
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in float value; // concentration as a fraction of the peak
+
+out float i_value;
+
+void main(void) {
+    gl_Position = P * V * vec4(position, 1.0);
+    gl_PointSize = pointSize / gl_Position.w;
+    i_value = value;
+}
@@ -0,0 +1,14 @@
+#version 330
+
+#include "common/frame.glsl"
+
+uniform mat4 M; // unit square to the slice plane
+
+layout(location = 0) in vec2 corner;
+
+out vec2 i_tc;
+
+void main(void) {
+    gl_Position = P * V * M * vec4(corner, 0.0, 1.0);
+    i_tc = corner;
+}
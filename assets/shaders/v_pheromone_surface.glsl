@@ -0,0 +1,13 @@
+#version 330
+
+#include "common/frame.glsl"
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+out vec3 i_normal;
+
+void main(void) {
+    gl_Position = P * V * vec4(position, 1.0);
+    i_normal = normal;
+}
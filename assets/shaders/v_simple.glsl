@@ -1,16 +1,24 @@
 #version 330
 
-uniform mat4 P;
-uniform mat4 V;
-uniform mat4 M;
+// With INSTANCED, the model matrix and colour are taken per instance.
+
+#include "common/frame.glsl"
 
 layout(location = 0) in vec4 vertex;
 layout(location = 1) in vec4 normal;
+layout(location = 2) in vec2 texCoord;
+#ifdef INSTANCED
+layout(location = 3) in mat4 M;     // locations 3-6
+layout(location = 7) in vec4 color;
+#else
+uniform mat4 M;
+uniform vec4 color = vec4(1.0);
+#endif
 
 out vec3 FragPos;
 out vec3 Normal;
-layout(location = 2) in vec2 texCoord;
 out vec2 TexCoord;
+out vec4 Color;
 
 void main(void) {
     mat4 MV = V * M;
@@ -18,4 +26,5 @@ void main(void) {
     Normal = mat3(transpose(inverse(MV))) * normal.xyz; // transformed normal
     gl_Position = P * MV * vertex;
     TexCoord = texCoord;
+    Color = color;
 }
@@ -0,0 +1,101 @@
+// Timings for the spatial index and for whole world steps at swarm scale.
+// Run with `cargo bench --bench spatial_index`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use cosmic_ant_sim::simulation::caste::Caste;
+use cosmic_ant_sim::simulation::spatial::SpatialGrid;
+use cosmic_ant_sim::simulation::rng::{self, random_in_sphere, random_on_sphere};
+use cosmic_ant_sim::simulation::world::{World, FIXED_DT};
+use nalgebra_glm as glm;
+
+const CELL_SIZE: f32 = 1.0;
+const QUERY_RADIUS: f32 = 0.75;
+const SEED: u64 = 1;
+
+fn time<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn report(label: &str, count: usize, per_iteration: Duration) {
+    println!("{:<34} {:>7} ants  {:>9.3} ms", label, count, per_iteration.as_secs_f64() * 1000.0);
+}
+
+fn bench_index(count: usize) {
+    // Grow the world with the swarm so the density stays at 1000 ants per sphere
+    // of radius 3, i.e. every ant has a few dozen others within sensing range.
+    let radius = 3.0 * (count as f32 / 1000.0).cbrt();
+    let mut rng = rng::substream(SEED, rng::WORLD_STREAM);
+    let positions: Vec<glm::Vec3> = (0..count).map(|_| random_in_sphere(&mut rng, radius)).collect();
+    let mut index = SpatialGrid::new(CELL_SIZE);
+
+    report("rebuild", count, time(20, || index.rebuild(positions.iter().copied())));
+
+    let mut out = Vec::new();
+    report(
+        "radius query for every ant",
+        count,
+        time(5, || {
+            for position in &positions {
+                index.query_radius(position, QUERY_RADIUS, &mut out);
+                black_box(out.len());
+            }
+        }),
+    );
+
+    report(
+        "8-nearest query for every ant",
+        count,
+        time(5, || {
+            for position in &positions {
+                black_box(index.k_nearest(position, 8, QUERY_RADIUS));
+            }
+        }),
+    );
+
+    let rays: Vec<(glm::Vec3, glm::Vec3)> = (0..1000)
+        .map(|_| (random_on_sphere(&mut rng, radius * 1.5), random_on_sphere(&mut rng, 1.0)))
+        .collect();
+    report(
+        "1000 raycasts",
+        count,
+        time(5, || {
+            for (origin, direction) in &rays {
+                black_box(index.raycast(origin, &(-origin + direction), radius * 3.0, 0.1));
+            }
+        }),
+    );
+}
+
+fn bench_world(count: usize) {
+    let scale = (count as f32 / 1000.0).cbrt();
+    let mut world = World::new(3.0 * scale, SEED);
+    // Every ant needs a home nest, or foraging skips it.
+    let nest = world.add_nest(glm::vec3(0.0, 0.0, 0.0), 0.5 * scale);
+    world.populate_nest(nest, Caste::Queen, 1);
+    world.populate_nest(nest, Caste::Soldier, count / 10);
+    world.populate_nest(nest, Caste::Scout, count / 10);
+    world.populate_nest(nest, Caste::Forager, count - 1 - 2 * (count / 10));
+    world.respawn_food_sources(20);
+    // Let pheromone build up before measuring.
+    for _ in 0..60 {
+        world.step(FIXED_DT);
+    }
+    report("World::step", count, time(60, || world.step(FIXED_DT)));
+}
+
+fn main() {
+    for count in [1_000, 10_000, 100_000] {
+        bench_index(count);
+        println!();
+    }
+    for count in [1_000, 10_000] {
+        bench_world(count);
+    }
+}
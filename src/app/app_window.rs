@@ -1,13 +1,30 @@
+use super::camera::Camera;
+use super::capture::{self, CaptureConfig, FrameRecorder};
+use super::follow;
+use super::picking;
+use super::render_bench::RenderBench;
 use super::renderer::Renderer;
+use super::ui::{Controls, Requests, Ui};
+use crate::simulation::caste::Caste;
+use crate::simulation::export::{ExportConfig, Exporter};
+use crate::simulation::replay::{Input, Timeline};
+use crate::simulation::snapshot;
+use crate::simulation::stats::Stats;
+use crate::simulation::timestep::FixedTimestep;
+use crate::simulation::world::{World, FIXED_DT};
 use std::error::Error;
 use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use nalgebra_glm as glm;
 
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, KeyEvent, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::{Key, KeyCode, NamedKey, PhysicalKey};
+use winit::event::{ElementState, KeyEvent, MouseScrollDelta, WindowEvent};
+use winit::event_loop::ActiveEventLoop;
+use winit::keyboard::{Key, NamedKey, PhysicalKey};
 use winit::raw_window_handle::HasWindowHandle;
-use winit::window::{Window, WindowAttributes, WindowId};
+use winit::window::{Window, WindowAttributes};
 
 use glutin::config::{Config, ConfigTemplateBuilder, GetGlConfig};
 use glutin::context::{
@@ -19,6 +36,20 @@ use glutin::surface::{Surface, SwapInterval, WindowSurface};
 
 use glutin_winit::{DisplayBuilder, GlWindow};
 
+/// Written by F5 and read back by F9, relative to the working directory.
+const QUICKSAVE_PATH: &str = "quicksave.antsnap";
+/// Written by F6; play it back with `--replay`.
+const RECORDING_PATH: &str = "recording.antreplay";
+/// Ticks PageUp/PageDown scrub by.
+const SCRUB_TICKS: u64 = 600;
+pub const MIN_PLAYBACK_RATE: f32 = 1.0 / 16.0;
+pub const MAX_PLAYBACK_RATE: f32 = 8.0;
+/// Touchpads scroll in pixels; this many make one wheel line.
+const WHEEL_PIXELS_PER_LINE: f32 = 40.0;
+const WINDOW_TITLE: &str = "Cosmic Ant Simulator (press Escape to exit)";
+/// How often shader files are checked for changes to reload.
+const SHADER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 pub struct AppState {
     gl_surface: Surface<WindowSurface>,
     // NOTE: Window should be dropped after all resources created using its
@@ -29,6 +60,31 @@ pub struct AppState {
 pub struct App {
     template: ConfigTemplateBuilder,
     renderer: Option<Renderer>,
+    ui: Option<Ui>,
+    camera: Camera,
+    timeline: Timeline,
+    /// Samples of the timeline's world for the statistics plots.
+    stats: Stats,
+    /// Where F7 exports go and what they write.
+    export_dir: PathBuf,
+    export_config: ExportConfig,
+    exporter: Option<Exporter>,
+    capture: CaptureConfig,
+    /// Save the next frame drawn, set by F12.
+    screenshot: bool,
+    /// Running frame recording, toggled by F8.
+    frame_recorder: Option<FrameRecorder>,
+    /// Builds the world a reset restarts from, for a seed.
+    reset: Box<dyn Fn(u64) -> World>,
+    timestep: FixedTimestep,
+    last_frame: Instant,
+    paused: bool,
+    /// When the shader files were last checked for changes.
+    last_shader_poll: Instant,
+    /// Simulated seconds per real second.
+    playback_rate: f32,
+    /// Replaces the simulation when `--render-bench` is given.
+    render_bench: Option<RenderBench>,
     // NOTE: `AppState` carries the `Window`, thus it should be dropped after everything else.
     state: Option<AppState>,
     gl_context: Option<PossiblyCurrentContext>,
@@ -37,15 +93,168 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(template: ConfigTemplateBuilder, display_builder: DisplayBuilder) -> Self {
+    pub fn new(
+        template: ConfigTemplateBuilder,
+        display_builder: DisplayBuilder,
+        timeline: Timeline,
+        reset: Box<dyn Fn(u64) -> World>,
+    ) -> Self {
         Self {
             template,
-            gl_display: GlDisplayCreationState::Builder(display_builder),
+            gl_display: GlDisplayCreationState::Builder(Box::new(display_builder)),
             exit_state: Ok(()),
             gl_context: None,
             state: None,
             renderer: None,
+            ui: None,
+            camera: Camera::new(1, 1),
+            timeline,
+            stats: Stats::default(),
+            export_dir: PathBuf::from("output"),
+            export_config: ExportConfig::default(),
+            exporter: None,
+            capture: CaptureConfig::default(),
+            screenshot: false,
+            frame_recorder: None,
+            reset,
+            timestep: FixedTimestep::new(FIXED_DT),
+            last_frame: Instant::now(),
+            paused: false,
+            last_shader_poll: Instant::now(),
+            playback_rate: 1.0,
+            render_bench: None,
+        }
+    }
+
+    /// Draws the benchmark swarms instead of the timeline, then exits.
+    pub fn run_render_bench(&mut self, bench: RenderBench) {
+        self.render_bench = Some(bench);
+    }
+
+    /// Where exports started with F7 or the statistics panel are written, in
+    /// a new directory each.
+    pub fn set_export(&mut self, dir: PathBuf, config: ExportConfig) {
+        self.export_dir = dir;
+        self.export_config = config;
+    }
+
+    /// Where screenshots and frame recordings go and how recordings are paced.
+    pub fn set_capture(&mut self, config: CaptureConfig) {
+        self.capture = config;
+    }
+
+    fn toggle_frame_recording(&mut self) {
+        match self.frame_recorder.take() {
+            Some(recorder) => {
+                let dir = recorder.dir().to_path_buf();
+                println!("Recorded {} frames to {}", recorder.finish(), dir.display());
+                // Don't catch up on the time spent recording.
+                self.timestep = FixedTimestep::new(FIXED_DT);
+            },
+            None => match FrameRecorder::start(&self.capture, FIXED_DT) {
+                Ok(recorder) => {
+                    println!("Recording frames to {}", recorder.dir().display());
+                    self.frame_recorder = Some(recorder);
+                },
+                Err(err) => eprintln!("Recording failed: {}", err),
+            },
+        }
+    }
+
+    fn toggle_export(&mut self) {
+        if self.exporter.is_some() {
+            self.stop_export();
+            return;
+        }
+        let started = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        let dir = self.export_dir.join(format!("export_{}", started.as_secs()));
+        match Exporter::create(&dir, &self.export_config) {
+            Ok(exporter) => {
+                println!("Exporting to {}", dir.display());
+                self.exporter = Some(exporter);
+            },
+            Err(err) => eprintln!("Export failed: {}", err),
+        }
+    }
+
+    /// Writes the last sample of a running export and closes its files.
+    fn stop_export(&mut self) {
+        let Some(exporter) = self.exporter.take() else {
+            return;
+        };
+        let dir = exporter.dir().to_path_buf();
+        match exporter.finish(self.timeline.world()) {
+            Ok(_) => println!("Exported up to tick {} to {}", self.timeline.tick(), dir.display()),
+            Err(err) => eprintln!("Export failed: {}", err),
+        }
+    }
+
+    fn seek(&mut self, tick: u64) {
+        if let Err(err) = self.timeline.seek(tick) {
+            eprintln!("Seek failed: {}", err);
+        }
+        self.timestep = FixedTimestep::new(FIXED_DT);
+        self.print_playback();
+    }
+
+    /// Handles the keys that pick the ant to follow and the follow view;
+    /// returns whether `key` is one of them.
+    fn follow_key(&mut self, key: &str) -> bool {
+        let world = self.timeline.world();
+        let current = self.camera.followed();
+        let ant = match key.to_ascii_lowercase().as_str() {
+            "f" if current.is_some() => {
+                self.camera.cycle_follow_view();
+                println!("Camera: {}", self.camera.mode().name());
+                return true;
+            },
+            "f" | "." => follow::step_ant(world, current, 1),
+            "," => follow::step_ant(world, current, -1),
+            "n" => {
+                let caste = current.and_then(|id| follow::find_ant(world, id)).map_or(Caste::Worker, |ant| ant.caste);
+                follow::next_of_caste(world, current, caste)
+            },
+            "k" => follow::next_caste(world, current),
+            "g" => follow::random_ant(world),
+            _ => return false,
+        };
+        match ant {
+            Some(ant) => {
+                self.camera.follow(ant);
+                println!("Following ant {} ({} camera)", ant, self.camera.mode().name());
+            },
+            None => println!("No ants to follow"),
         }
+        true
+    }
+
+    /// Selects whatever is under the cursor, or clears the selection, and
+    /// prints what was selected.
+    fn select_at_cursor(&mut self) {
+        let Some(ray) = self.camera.cursor_ray() else {
+            return;
+        };
+        self.renderer.as_mut().unwrap().selection = picking::pick(self.timeline.world(), &ray);
+    }
+
+    /// Heading and readout of the followed ant, if any, for the overlay.
+    fn follow_overlay(&self) -> Option<(String, String)> {
+        let world = self.timeline.world();
+        let ant = self.camera.followed().and_then(|id| follow::find_ant(world, id))?;
+        Some((format!("{} camera", self.camera.mode().name()), follow::readout(world, ant)))
+    }
+
+    fn print_playback(&self) {
+        let recording = self.timeline.recording();
+        println!(
+            "Tick {} of {}-{}{}, {}x{}",
+            self.timeline.tick(),
+            recording.start_tick(),
+            recording.end_tick(),
+            if self.timeline.is_replaying() { " (replaying)" } else { "" },
+            self.playback_rate,
+            if self.paused { ", paused" } else { "" },
+        );
     }
 }
 
@@ -107,14 +316,37 @@ impl ApplicationHandler for App {
         let gl_context = self.gl_context.as_ref().unwrap();
         gl_context.make_current(&gl_surface).unwrap();
 
-        self.renderer.get_or_insert_with(|| Renderer::new(&gl_config.display()));
+        if self.renderer.is_none() {
+            match Renderer::new(&gl_config.display()) {
+                Ok(renderer) => self.renderer = Some(renderer),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.exit_state = Err(err.into());
+                    event_loop.exit();
+                    return;
+                },
+            }
+        }
+        let renderer = self.renderer.as_mut().unwrap();
+        let size = window.inner_size();
+        renderer.resize(size.width as i32, size.height as i32);
+        self.camera.set_viewport(size.width, size.height);
+        if self.ui.is_none() {
+            match Ui::new(&window, self.timeline.world().seed()) {
+                Ok(ui) => self.ui = Some(ui),
+                // The simulation still runs without panels.
+                Err(err) => eprintln!("{}", err),
+            }
+        }
 
 
 
-        // Try setting vsync.
-        if let Err(res) = gl_surface
-            .set_swap_interval(gl_context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()))
-        {
+        // Try setting vsync; benchmarks want every frame as fast as it goes.
+        let interval = match self.render_bench {
+            Some(_) => SwapInterval::DontWait,
+            None => SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
+        };
+        if let Err(res) = gl_surface.set_swap_interval(gl_context, interval) {
             eprintln!("Error setting vsync: {res:?}");
         }
 
@@ -142,6 +374,33 @@ impl ApplicationHandler for App {
         _window_id: winit::window::WindowId,
         event: WindowEvent,
     ) {
+        let consumed = match (self.ui.as_mut(), self.state.as_ref()) {
+            (Some(ui), Some(AppState { window, .. })) => ui.on_window_event(window, &event),
+            _ => false,
+        };
+        if consumed {
+            // Releases still reach the camera, so nothing stays held.
+            match event {
+                WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                    self.camera.mouse_button(button, false);
+                },
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Released, .. },
+                    ..
+                } => {
+                    self.camera.key(code, false);
+                },
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.camera.cursor_moved(glm::vec2(position.x, position.y));
+                },
+                _ => (),
+            }
+            return;
+        }
+        // Movement keys are tracked while held, whatever else they do.
+        if let WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(code), state, .. }, .. } = &event {
+            self.camera.key(*code, *state == ElementState::Pressed);
+        }
         match event {
             WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
                 // Some platforms like EGL require resizing GL surface to update the size
@@ -159,62 +418,201 @@ impl ApplicationHandler for App {
                     let renderer = self.renderer.as_ref().unwrap();
                     renderer.resize(size.width as i32, size.height as i32);
                 }
+                self.camera.set_viewport(size.width, size.height);
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                let clicked = self.camera.mouse_button(button, state == ElementState::Pressed);
+                if clicked {
+                    self.select_at_cursor();
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.camera.cursor_moved(glm::vec2(position.x, position.y));
+            },
+            WindowEvent::CursorLeft { .. } => self.camera.cursor_left(),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / WHEEL_PIXELS_PER_LINE,
+                };
+                self.camera.scroll(lines);
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::Tab), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                self.camera.toggle_mode();
+                println!("Camera: {}", self.camera.mode().name());
+
             },
             WindowEvent::KeyboardInput {
                 event: KeyEvent { logical_key: Key::Character(c), state: ElementState::Pressed, ..},
                 ..
             } if c.eq_ignore_ascii_case("r") => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.generateRandomPos();
+                let count = self.timeline.world().food_sources.len() as u32;
+                self.timeline.apply(Input::RespawnFood { count });
 
             },
             WindowEvent::KeyboardInput {
-                event: KeyEvent { logical_key: Key::Character(s), state: ElementState::Pressed, ..},
+                event: KeyEvent { logical_key: Key::Character(c), state: ElementState::Pressed, ..},
                 ..
-            } if s.eq_ignore_ascii_case("s") => {
+            } if c.eq_ignore_ascii_case("i") => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.speed = 0.0;
+                let renderer = self.renderer.as_mut().unwrap();
+                renderer.instancing = !renderer.instancing;
+                println!("Instanced drawing {}", if renderer.instancing { "on" } else { "off" });
 
             },
             WindowEvent::KeyboardInput {
-                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowRight), state: ElementState::Pressed, ..},
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F1), state: ElementState::Pressed, ..},
                 ..
             } => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.speed = 0.05;
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.visible = !ui.visible;
+                }
 
             },
             WindowEvent::KeyboardInput {
-                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowLeft), state: ElementState::Pressed, ..},
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F5), state: ElementState::Pressed, ..},
                 ..
             } => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.speed = -0.05;
+                match snapshot::save_to_file(self.timeline.world(), QUICKSAVE_PATH) {
+                    Ok(()) => println!("Saved tick {} to {}", self.timeline.tick(), QUICKSAVE_PATH),
+                    Err(err) => eprintln!("Quick-save failed: {}", err),
+                }
 
             },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F9), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                match snapshot::load_from_file(QUICKSAVE_PATH) {
+                    Ok(world) => {
+                        // Loading starts a new recording from the loaded state.
+                        println!("Loaded tick {} from {}", world.tick, QUICKSAVE_PATH);
+                        self.stop_export();
+                        self.timeline = Timeline::new(world);
+                        self.stats.clear();
+                        self.timestep = FixedTimestep::new(FIXED_DT);
+                    },
+                    Err(err) => eprintln!("Quick-load failed: {}", err),
+                }
 
+            },
             WindowEvent::KeyboardInput {
-                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowUp), state: ElementState::Pressed, ..},
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F6), state: ElementState::Pressed, ..},
                 ..
             } => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.zoom += 0.1;
-                renderer.changeCameraZoom();
+                match self.timeline.recording().save_to_file(RECORDING_PATH) {
+                    Ok(()) => println!("Saved recording up to tick {} to {}", self.timeline.recording().end_tick(), RECORDING_PATH),
+                    Err(err) => eprintln!("Saving the recording failed: {}", err),
+                }
 
             },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F7), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                self.toggle_export();
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F12), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                self.screenshot = true;
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::F8), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                self.toggle_frame_recording();
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::Space), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                self.paused = !self.paused;
+                self.print_playback();
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowRight), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                // Steps one tick while paused, plays faster otherwise.
+                if self.paused {
+                    self.timeline.step();
+                } else {
+                    self.playback_rate = (self.playback_rate * 2.0).min(MAX_PLAYBACK_RATE);
+                }
+                self.print_playback();
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowLeft), state: ElementState::Pressed, ..},
+                ..
+            } => {
+
+                // Steps one tick back while paused, plays slower otherwise.
+                if self.paused {
+                    self.seek(self.timeline.tick().saturating_sub(1));
+                } else {
+                    self.playback_rate = (self.playback_rate / 2.0).max(MIN_PLAYBACK_RATE);
+                    self.print_playback();
+                }
+
+            },
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::PageDown), state: ElementState::Pressed, ..},
+                ..
+            } => self.seek(self.timeline.tick().saturating_sub(SCRUB_TICKS)),
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::PageUp), state: ElementState::Pressed, ..},
+                ..
+            } => self.seek(self.timeline.tick() + SCRUB_TICKS),
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::Home), state: ElementState::Pressed, ..},
+                ..
+            } => self.seek(self.timeline.recording().start_tick()),
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::End), state: ElementState::Pressed, ..},
+                ..
+            } => self.seek(self.timeline.recording().end_tick()),
+
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowUp), state: ElementState::Pressed, ..},
+                ..
+            } => self.camera.scroll(1.0),
             WindowEvent::KeyboardInput {
                 event: KeyEvent { logical_key: Key::Named(NamedKey::ArrowDown), state: ElementState::Pressed, ..},
                 ..
+            } => self.camera.scroll(-1.0),
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { logical_key: Key::Character(c), state: ElementState::Pressed, ..},
+                ..
             } => {
 
-                let mut renderer = self.renderer.as_mut().unwrap();
-                renderer.zoom -= 0.1;
-                renderer.changeCameraZoom();
+                if self.follow_key(&c) {
+                    return;
+                }
+                let pheromones = &mut self.renderer.as_mut().unwrap().pheromones;
+                if pheromones.handle_key(&c) {
+                    println!("{}", pheromones.describe());
+                }
 
             },
             WindowEvent::CloseRequested
@@ -228,6 +626,11 @@ impl ApplicationHandler for App {
     }
 
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.stop_export();
+        if self.frame_recorder.is_some() {
+            self.toggle_frame_recording();
+        }
+
         // NOTE: The handling below is only needed due to nvidia on Wayland to not crash
         // on exit due to nvidia driver touching the Wayland display from on
         // `exit` hook.
@@ -235,7 +638,7 @@ impl ApplicationHandler for App {
 
         // Clear the window.
         self.state = None;
-        #[cfg(egl_backend)]
+        #[cfg(any(windows, all(unix, not(target_vendor = "apple"))))]
         #[allow(irrefutable_let_patterns)]
         if let glutin::display::Display::Egl(display) = _gl_display {
             unsafe {
@@ -244,15 +647,110 @@ impl ApplicationHandler for App {
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let mut requests = Requests::default();
         if let Some(AppState { gl_surface, window }) = self.state.as_ref() {
             let gl_context = self.gl_context.as_ref().unwrap();
+            if let Some(bench) = self.render_bench.as_mut() {
+                let renderer = self.renderer.as_mut().unwrap();
+                let start = Instant::now();
+                renderer.instancing = bench.instanced();
+                renderer.draw(bench.world(), &self.camera);
+                gl_surface.swap_buffers(gl_context).unwrap();
+                unsafe {
+                    gl::Finish();
+                }
+                if !bench.record(start.elapsed()) {
+                    print!("{}", bench.report());
+                    event_loop.exit();
+                }
+                window.request_redraw();
+                return;
+            }
+            let now = Instant::now();
+            let frame_time = now.duration_since(self.last_frame).as_secs_f32();
+            self.last_frame = now;
+            if !self.paused {
+                // A recording steps the same number of ticks every frame,
+                // however long frames take.
+                let ticks = match self.frame_recorder.as_ref() {
+                    Some(recorder) => recorder.ticks_per_frame(),
+                    None => self.timestep.advance(frame_time * self.playback_rate),
+                };
+                for _ in 0..ticks {
+                    self.timeline.step();
+                    record(self.timeline.world(), &mut self.stats, &mut self.exporter);
+                }
+            }
+            // Seeking and stepping from the panels happen outside the loop.
+            record(self.timeline.world(), &mut self.stats, &mut self.exporter);
+
+            self.camera.update(frame_time, self.timeline.world());
+            let follow = self.follow_overlay();
+
             let renderer = self.renderer.as_mut().unwrap();
-            renderer.draw();
+            if now.duration_since(self.last_shader_poll) >= SHADER_POLL_INTERVAL {
+                self.last_shader_poll = now;
+                renderer.reload_shaders();
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.reload_shaders();
+                }
+            }
+            renderer.draw(self.timeline.world(), &self.camera);
+            // Screenshots and recordings show the scene without the panels.
+            let record_frame = self.frame_recorder.is_some() && !self.paused;
+            if self.screenshot || record_frame {
+                let size = window.inner_size();
+                let image = capture::read_pixels(size.width, size.height);
+                if std::mem::take(&mut self.screenshot) {
+                    match capture::save_screenshot(&self.capture.dir, &image) {
+                        Ok(path) => println!("Saved screenshot to {}", path.display()),
+                        Err(err) => eprintln!("Screenshot failed: {}", err),
+                    }
+                }
+                let recorded = self.frame_recorder.as_mut().filter(|_| record_frame).map(|recorder| recorder.add_frame(&image));
+                if let Some(Err(err)) = recorded {
+                    eprintln!("Recording failed: {}", err);
+                    self.frame_recorder = None;
+                }
+            }
+            if let Some(ui) = self.ui.as_mut() {
+                let controls = Controls {
+                    timeline: &mut self.timeline,
+                    paused: &mut self.paused,
+                    playback_rate: &mut self.playback_rate,
+                    renderer,
+                    stats: &self.stats,
+                    export: self.exporter.as_ref().map(Exporter::dir),
+                    follow,
+                };
+                requests = ui.frame(window, controls);
+            }
             window.request_redraw();
 
             gl_surface.swap_buffers(gl_context).unwrap();
         }
+
+        if requests.toggle_export {
+            self.toggle_export();
+        }
+        if let Some(seed) = requests.reset {
+            println!("Reset with seed {}", seed);
+            self.stop_export();
+            self.timeline = Timeline::new((self.reset)(seed));
+            self.stats.clear();
+            self.timestep = FixedTimestep::new(FIXED_DT);
+        }
+    }
+}
+
+/// Samples `world` into the statistics and any running export, which is
+/// dropped if it fails.
+fn record(world: &World, stats: &mut Stats, exporter: &mut Option<Exporter>) {
+    stats.record(world);
+    if let Some(Err(err)) = exporter.as_mut().map(|exporter| exporter.record(world)) {
+        eprintln!("Export failed: {}", err);
+        *exporter = None;
     }
 }
 
@@ -295,12 +793,12 @@ pub fn create_gl_context(window: &Window, gl_config: &Config) -> NotCurrentConte
 pub fn window_attributes() -> WindowAttributes {
     Window::default_attributes()
         .with_transparent(true)
-        .with_title("Cosmic Ant Simulator (press Escape to exit)")
+        .with_title(WINDOW_TITLE)
 }
 
 pub enum GlDisplayCreationState {
     /// The display was not build yet.
-    Builder(DisplayBuilder),
+    Builder(Box<DisplayBuilder>),
     /// The display was already created for the application.
     Init,
 }
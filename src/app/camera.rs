@@ -0,0 +1,494 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use nalgebra_glm as glm;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use cosmic_ant_sim::simulation::ant::Ant;
+use cosmic_ant_sim::simulation::world::World;
+
+use super::follow::{self, FollowView};
+use super::picking::Ray;
+use super::renderer::caste_appearance;
+
+const FOV_DEGREES: f32 = 100.0;
+const NEAR: f32 = 0.05;
+const FAR: f32 = 50.0;
+const UP: glm::Vec3 = glm::Vec3::new(0.0, 1.0, 0.0);
+/// Keeps the camera off the poles, where yaw is undefined.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Radians turned per pixel of mouse movement.
+const ROTATE_SPEED: f32 = 0.005;
+/// Orbit target movement per pixel, as a fraction of the orbit distance.
+const PAN_SPEED: f32 = 0.0015;
+/// Orbit distance factor per wheel line.
+const ZOOM_STEP: f32 = 0.9;
+const MIN_DISTANCE: f32 = 0.3;
+const MAX_DISTANCE: f32 = 40.0;
+const INITIAL_DISTANCE: f32 = 5.0;
+
+/// Free-fly speed in world units per second, and its range for the wheel.
+const FLY_SPEED: f32 = 1.5;
+const MIN_FLY_SPEED: f32 = 0.1;
+const MAX_FLY_SPEED: f32 = 20.0;
+/// Speed factor while Shift is held.
+const FAST_FACTOR: f32 = 4.0;
+
+/// Rates, per second, at which the camera closes on where the input puts it.
+/// Higher is snappier; the damping is `1 - exp(-rate * dt)` per frame, so it
+/// feels the same at any frame rate.
+const ROTATE_DAMPING: f32 = 15.0;
+const MOVE_DAMPING: f32 = 8.0;
+const FOLLOW_DAMPING: f32 = 6.0;
+
+/// Follow camera offsets, in lengths of the followed ant's mesh: the chase
+/// camera sits behind and above the ant and looks at a point ahead of it.
+const CHASE_BACK: f32 = 4.0;
+const CHASE_UP: f32 = 1.5;
+const CHASE_LOOK_AHEAD: f32 = 2.0;
+/// Where the first-person camera sits, from the ant's centre.
+const HEAD_FORWARD: f32 = 0.5;
+const HEAD_UP: f32 = 0.2;
+/// Orbit distance when starting to orbit an ant.
+const FOLLOW_ORBIT_DISTANCE: f32 = 6.0;
+
+/// A left press and release this many pixels apart or closer is a click
+/// rather than a drag.
+const CLICK_SLOP: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Circles a target point: drag to rotate, wheel to zoom, middle-drag to pan.
+    Orbit,
+    /// Flies freely: WASD to move, Q/E down and up, drag to look, wheel for speed.
+    FreeFly,
+    /// Locked onto one ant, see `FollowView`.
+    Follow(FollowView),
+}
+
+impl CameraMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Orbit => "orbit",
+            CameraMode::FreeFly => "free-fly",
+            CameraMode::Follow(view) => view.name(),
+        }
+    }
+}
+
+/// Direction at `yaw` around the y axis and `pitch` above the horizon; yaw 0
+/// looks down +z.
+fn direction(yaw: f32, pitch: f32) -> glm::Vec3 {
+    glm::vec3(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos())
+}
+
+/// Moves `current` toward `goal` by the share of the way `rate` covers in `dt`.
+fn damp<T>(current: T, goal: T, rate: f32, dt: f32) -> T
+where
+    T: Copy + std::ops::Add<Output = T> + std::ops::Sub<Output = T> + std::ops::Mul<f32, Output = T>,
+{
+    current + (goal - current) * (1.0 - (-rate * dt).exp())
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OrbitPose {
+    target: glm::Vec3,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl OrbitPose {
+    /// The pose looking at `target` from `eye`.
+    fn looking_at(eye: glm::Vec3, target: glm::Vec3) -> Self {
+        let offset = eye - target;
+        let distance = glm::length(&offset).max(MIN_DISTANCE);
+        let pitch = (offset.y / distance).clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        OrbitPose { target, yaw: offset.x.atan2(offset.z), pitch, distance }
+    }
+
+    fn eye(&self) -> glm::Vec3 {
+        self.target + direction(self.yaw, self.pitch) * self.distance
+    }
+}
+
+/// Where the orbit camera is, and where input is taking it.
+struct Orbit {
+    current: OrbitPose,
+    goal: OrbitPose,
+}
+
+/// Where the free-fly camera is and looks, and where input is taking it.
+struct FreeFly {
+    position: glm::Vec3,
+    velocity: glm::Vec3,
+    yaw: f32,
+    pitch: f32,
+    goal_yaw: f32,
+    goal_pitch: f32,
+    speed: f32,
+}
+
+/// The ant being followed, where it was last seen, and the view of it.
+struct Followed {
+    ant: u32,
+    position: glm::Vec3,
+    /// Length of the ant's mesh, which the follow offsets are measured in.
+    length: f32,
+    eye: glm::Vec3,
+    target: glm::Vec3,
+    up: glm::Vec3,
+}
+
+/// Keys and buttons the camera reacts to while they are held.
+#[derive(Default)]
+struct Held {
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    fast: bool,
+    rotate: bool,
+    pan: bool,
+}
+
+/// The view and projection, driven by mouse and keyboard through one of the
+/// `CameraMode` controllers. Input arrives as window events and is applied,
+/// smoothed, by `update` once per frame.
+pub struct Camera {
+    mode: CameraMode,
+    orbit: Orbit,
+    fly: FreeFly,
+    followed: Option<Followed>,
+    held: Held,
+    cursor: Option<glm::DVec2>,
+    /// Where the left button went down, while it is held.
+    press: Option<glm::DVec2>,
+    /// Cursor movement while rotating and panning since the last update, in pixels.
+    rotate_delta: glm::Vec2,
+    pan_delta: glm::Vec2,
+    /// Wheel lines since the last update.
+    scroll: f32,
+    /// Viewport size in pixels.
+    viewport: glm::Vec2,
+}
+
+impl Camera {
+    /// A camera orbiting the origin, for a viewport of `width` x `height` pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        let pose = OrbitPose { target: glm::Vec3::zeros(), yaw: PI, pitch: 0.0, distance: INITIAL_DISTANCE };
+        let mut camera = Camera {
+            mode: CameraMode::Orbit,
+            orbit: Orbit { current: pose, goal: pose },
+            fly: FreeFly {
+                position: pose.eye(),
+                velocity: glm::Vec3::zeros(),
+                yaw: 0.0,
+                pitch: 0.0,
+                goal_yaw: 0.0,
+                goal_pitch: 0.0,
+                speed: FLY_SPEED,
+            },
+            followed: None,
+            held: Held::default(),
+            cursor: None,
+            press: None,
+            rotate_delta: glm::Vec2::zeros(),
+            pan_delta: glm::Vec2::zeros(),
+            scroll: 0.0,
+            viewport: glm::vec2(1.0, 1.0),
+        };
+        camera.set_viewport(width, height);
+        camera
+    }
+
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.viewport = glm::vec2(width as f32, height as f32);
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches between orbiting and flying, keeping the current view. Stops
+    /// following an ant, orbiting where it was.
+    pub fn toggle_mode(&mut self) {
+        match self.mode {
+            CameraMode::Follow(_) => self.stop_following(),
+            CameraMode::Orbit => {
+                let pose = self.orbit.current;
+                self.fly.position = pose.eye();
+                self.fly.velocity = glm::Vec3::zeros();
+                // Looking back at the target is looking the opposite way.
+                self.fly.yaw = pose.yaw + PI;
+                self.fly.pitch = -pose.pitch;
+                self.fly.goal_yaw = self.fly.yaw;
+                self.fly.goal_pitch = self.fly.pitch;
+                self.mode = CameraMode::FreeFly;
+            },
+            CameraMode::FreeFly => {
+                let distance = self.orbit.current.distance;
+                let pose = OrbitPose {
+                    target: self.fly.position + direction(self.fly.yaw, self.fly.pitch) * distance,
+                    yaw: self.fly.yaw + PI,
+                    pitch: -self.fly.pitch,
+                    distance,
+                };
+                self.orbit = Orbit { current: pose, goal: pose };
+                self.mode = CameraMode::Orbit;
+            },
+        }
+    }
+
+    /// Id of the ant the camera follows.
+    pub fn followed(&self) -> Option<u32> {
+        self.followed.as_ref().map(|followed| followed.ant)
+    }
+
+    /// Follows the ant with id `ant`, keeping the follow view if already
+    /// following another one and starting with the chase view otherwise.
+    pub fn follow(&mut self, ant: u32) {
+        match &mut self.followed {
+            Some(followed) => followed.ant = ant,
+            None => {
+                let (eye, target) = (self.eye(), self.target());
+                self.followed = Some(Followed { ant, position: target, length: 1.0, eye, target, up: UP });
+                self.mode = CameraMode::Follow(FollowView::Chase);
+            },
+        }
+    }
+
+    /// Moves on to the next follow view, and from the last one back to orbiting.
+    pub fn cycle_follow_view(&mut self) {
+        let CameraMode::Follow(view) = self.mode else {
+            return;
+        };
+        match view.next() {
+            Some(FollowView::Orbit) => {
+                let followed = self.followed.as_ref().unwrap();
+                let mut pose = OrbitPose::looking_at(followed.eye, followed.position);
+                pose.distance = FOLLOW_ORBIT_DISTANCE * followed.length;
+                self.orbit = Orbit { current: pose, goal: pose };
+                self.mode = CameraMode::Follow(FollowView::Orbit);
+            },
+            Some(next) => self.mode = CameraMode::Follow(next),
+            None => self.stop_following(),
+        }
+    }
+
+    /// Ant the camera is inside of and which shouldn't be drawn.
+    pub fn hidden_ant(&self) -> Option<u32> {
+        match self.mode {
+            CameraMode::Follow(FollowView::FirstPerson) => self.followed(),
+            _ => None,
+        }
+    }
+
+    fn stop_following(&mut self) {
+        if self.mode != CameraMode::Follow(FollowView::Orbit) {
+            let pose = OrbitPose::looking_at(self.eye(), self.target());
+            self.orbit = Orbit { current: pose, goal: pose };
+        }
+        self.followed = None;
+        self.mode = CameraMode::Orbit;
+    }
+
+    /// Tracks the movement keys; returns whether `code` is one.
+    pub fn key(&mut self, code: KeyCode, pressed: bool) -> bool {
+        let held = match code {
+            KeyCode::KeyW => &mut self.held.forward,
+            KeyCode::KeyS => &mut self.held.back,
+            KeyCode::KeyA => &mut self.held.left,
+            KeyCode::KeyD => &mut self.held.right,
+            KeyCode::KeyE => &mut self.held.up,
+            KeyCode::KeyQ => &mut self.held.down,
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => &mut self.held.fast,
+            _ => return false,
+        };
+        *held = pressed;
+        true
+    }
+
+    /// Tracks the rotate and pan buttons; returns whether this released a
+    /// left click that didn't drag.
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) -> bool {
+        match button {
+            MouseButton::Left => {
+                self.held.rotate = pressed;
+                let press = std::mem::replace(&mut self.press, if pressed { self.cursor } else { None });
+                if !pressed {
+                    return press.zip(self.cursor).is_some_and(|(press, cursor)| glm::distance(&press, &cursor) <= CLICK_SLOP);
+                }
+            },
+            MouseButton::Middle => self.held.pan = pressed,
+            _ => (),
+        }
+        false
+    }
+
+    pub fn cursor_moved(&mut self, position: glm::DVec2) {
+        if let Some(last) = self.cursor {
+            let delta = glm::convert::<glm::DVec2, glm::Vec2>(position - last);
+            if self.held.rotate {
+                self.rotate_delta += delta;
+            }
+            if self.held.pan {
+                self.pan_delta += delta;
+            }
+        }
+        self.cursor = Some(position);
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.cursor = None;
+        self.press = None;
+    }
+
+    /// The ray from the eye through the cursor, by unprojecting the cursor
+    /// at the near and far planes through the inverse of `P * V`.
+    pub fn cursor_ray(&self) -> Option<Ray> {
+        let cursor = glm::convert::<glm::DVec2, glm::Vec2>(self.cursor?);
+        let ndc = glm::vec2(2.0 * cursor.x / self.viewport.x - 1.0, 1.0 - 2.0 * cursor.y / self.viewport.y);
+        let inverse = glm::inverse(&(self.projection() * self.view()));
+        let unproject = |depth: f32| {
+            let point = inverse * glm::vec4(ndc.x, ndc.y, depth, 1.0);
+            point.xyz() / point.w
+        };
+        let (near, far) = (unproject(-1.0), unproject(1.0));
+        Some(Ray { origin: near, direction: glm::normalize(&(far - near)) })
+    }
+
+    /// Wheel movement in lines, positive away from the user.
+    pub fn scroll(&mut self, lines: f32) {
+        self.scroll += lines;
+    }
+
+    /// Applies the input gathered since the last call and moves the camera
+    /// `dt` seconds further toward where it was sent. A followed ant that is
+    /// no longer in `world` is let go of.
+    pub fn update(&mut self, dt: f32, world: &World) {
+        let rotate = std::mem::replace(&mut self.rotate_delta, glm::Vec2::zeros()) * ROTATE_SPEED;
+        let pan = std::mem::replace(&mut self.pan_delta, glm::Vec2::zeros());
+        let scroll = std::mem::replace(&mut self.scroll, 0.0);
+
+        match self.mode {
+            CameraMode::Orbit => self.update_orbit(rotate, pan, scroll, dt),
+            CameraMode::FreeFly => {
+                let fly = &mut self.fly;
+                fly.goal_yaw -= rotate.x;
+                fly.goal_pitch = (fly.goal_pitch - rotate.y).clamp(-MAX_PITCH, MAX_PITCH);
+                fly.speed = (fly.speed / ZOOM_STEP.powf(scroll)).clamp(MIN_FLY_SPEED, MAX_FLY_SPEED);
+                fly.yaw = damp(fly.yaw, fly.goal_yaw, ROTATE_DAMPING, dt);
+                fly.pitch = damp(fly.pitch, fly.goal_pitch, ROTATE_DAMPING, dt);
+
+                let forward = direction(fly.yaw, fly.pitch);
+                let right = glm::normalize(&glm::cross(&forward, &UP));
+                let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+                let held = &self.held;
+                let mut wish = forward * axis(held.forward, held.back)
+                    + right * axis(held.right, held.left)
+                    + UP * axis(held.up, held.down);
+                if wish != glm::Vec3::zeros() {
+                    wish = glm::normalize(&wish) * fly.speed * if held.fast { FAST_FACTOR } else { 1.0 };
+                }
+                fly.velocity = damp(fly.velocity, wish, MOVE_DAMPING, dt);
+                fly.position += fly.velocity * dt;
+            },
+            CameraMode::Follow(view) => {
+                match self.followed().and_then(|id| follow::find_ant(world, id)) {
+                    Some(ant) => self.update_follow(view, ant, rotate, scroll, dt),
+                    None => self.stop_following(),
+                }
+            },
+        }
+    }
+
+    fn update_orbit(&mut self, rotate: glm::Vec2, pan: glm::Vec2, scroll: f32, dt: f32) {
+        let goal = &mut self.orbit.goal;
+        goal.yaw -= rotate.x;
+        goal.pitch = (goal.pitch + rotate.y).clamp(-MAX_PITCH, MAX_PITCH);
+        goal.distance = (goal.distance * ZOOM_STEP.powf(scroll)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        if pan != glm::Vec2::zeros() {
+            let forward = -direction(goal.yaw, goal.pitch);
+            let right = glm::normalize(&glm::cross(&forward, &UP));
+            let up = glm::cross(&right, &forward);
+            goal.target += (up * pan.y - right * pan.x) * goal.distance * PAN_SPEED;
+        }
+
+        let (current, goal) = (&mut self.orbit.current, self.orbit.goal);
+        current.target = damp(current.target, goal.target, MOVE_DAMPING, dt);
+        current.yaw = damp(current.yaw, goal.yaw, ROTATE_DAMPING, dt);
+        current.pitch = damp(current.pitch, goal.pitch, ROTATE_DAMPING, dt);
+        current.distance = damp(current.distance, goal.distance, MOVE_DAMPING, dt);
+    }
+
+    fn update_follow(&mut self, view: FollowView, ant: &Ant, rotate: glm::Vec2, scroll: f32, dt: f32) {
+        let length = caste_appearance(ant.caste).scale;
+        let (forward, up) = (ant.forward(), ant.up());
+        if view == FollowView::Orbit {
+            // The target stays on the ant, so only rotating and zooming apply.
+            self.orbit.goal.target = ant.position;
+            self.update_orbit(rotate, glm::Vec2::zeros(), scroll, dt);
+            self.orbit.current.target = ant.position;
+        }
+        let orbit_eye = self.orbit.current.eye();
+
+        let followed = self.followed.as_mut().unwrap();
+        followed.position = ant.position;
+        followed.length = length;
+        match view {
+            FollowView::Chase => {
+                let eye = ant.position - forward * CHASE_BACK * length + up * CHASE_UP * length;
+                let target = ant.position + forward * CHASE_LOOK_AHEAD * length;
+                followed.eye = damp(followed.eye, eye, FOLLOW_DAMPING, dt);
+                followed.target = damp(followed.target, target, ROTATE_DAMPING, dt);
+                followed.up = glm::normalize(&damp(followed.up, up, FOLLOW_DAMPING, dt));
+            },
+            FollowView::FirstPerson => {
+                // Locked to the head; any lag would put the camera outside the ant.
+                followed.eye = ant.position + (forward * HEAD_FORWARD + up * HEAD_UP) * length;
+                followed.target = followed.eye + forward;
+                followed.up = up;
+            },
+            FollowView::Orbit => {
+                followed.eye = orbit_eye;
+                followed.target = ant.position;
+                followed.up = UP;
+            },
+        }
+    }
+
+    fn eye(&self) -> glm::Vec3 {
+        match self.mode {
+            CameraMode::Orbit => self.orbit.current.eye(),
+            CameraMode::FreeFly => self.fly.position,
+            CameraMode::Follow(_) => self.followed.as_ref().unwrap().eye,
+        }
+    }
+
+    /// The point the camera looks at.
+    fn target(&self) -> glm::Vec3 {
+        match self.mode {
+            CameraMode::Orbit => self.orbit.current.target,
+            CameraMode::FreeFly => self.fly.position + direction(self.fly.yaw, self.fly.pitch),
+            CameraMode::Follow(_) => self.followed.as_ref().unwrap().target,
+        }
+    }
+
+    pub fn view(&self) -> glm::Mat4 {
+        let up = match (self.mode, &self.followed) {
+            (CameraMode::Follow(_), Some(followed)) => followed.up,
+            _ => UP,
+        };
+        glm::look_at(&self.eye(), &self.target(), &up)
+    }
+
+    pub fn projection(&self) -> glm::Mat4 {
+        glm::perspective(self.viewport.x / self.viewport.y, FOV_DEGREES.to_radians(), NEAR, FAR)
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gl::types::*;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+/// Speed of the GIF colour quantizer, from 1 (best) to 30 (fastest).
+const GIF_SPEED: i32 = 10;
+
+/// Where captures go and how recordings are paced.
+#[derive(Clone, Debug)]
+pub struct CaptureConfig {
+    pub dir: PathBuf,
+    /// Simulation ticks between recorded frames.
+    pub ticks_per_frame: u32,
+    /// Also encode recordings into `recording.gif`.
+    pub gif: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig { dir: PathBuf::from("captures"), ticks_per_frame: 2, gif: false }
+    }
+}
+
+/// Reads the bound read framebuffer into an opaque image, top row first.
+pub fn read_pixels(width: u32, height: u32) -> RgbaImage {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+    let mut image = RgbaImage::from_raw(width, height, pixels).unwrap();
+    image::imageops::flip_vertical_in_place(&mut image);
+    // The window may be transparent where nothing was drawn.
+    for pixel in image.pixels_mut() {
+        pixel[3] = u8::MAX;
+    }
+    image
+}
+
+/// Milliseconds since the epoch, to name captures.
+fn timestamp() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+fn create_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))
+}
+
+fn save_png(image: &RgbaImage, path: &Path) -> Result<(), String> {
+    image.save(path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes `image` to a new PNG in `dir` and returns its path.
+pub fn save_screenshot(dir: &Path, image: &RgbaImage) -> Result<PathBuf, String> {
+    create_dir(dir)?;
+    let path = dir.join(format!("screenshot_{}.png", timestamp()));
+    save_png(image, &path)?;
+    Ok(path)
+}
+
+/// Writes numbered PNG frames, and optionally a GIF, to a new directory. The
+/// app steps a fixed number of ticks per frame while one is running, so the
+/// result plays at simulation speed whatever the frame rate was.
+pub struct FrameRecorder {
+    dir: PathBuf,
+    ticks_per_frame: u32,
+    frames: u32,
+    gif: Option<GifEncoder<BufWriter<File>>>,
+    delay: Delay,
+}
+
+impl FrameRecorder {
+    /// Starts a recording of frames `tick_seconds * config.ticks_per_frame`
+    /// simulated seconds apart.
+    pub fn start(config: &CaptureConfig, tick_seconds: f32) -> Result<Self, String> {
+        let dir = config.dir.join(format!("recording_{}", timestamp()));
+        create_dir(&dir)?;
+        let ticks_per_frame = config.ticks_per_frame.max(1);
+        let gif = if config.gif {
+            let path = dir.join("recording.gif");
+            let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            Some(encoder)
+        } else {
+            None
+        };
+        let delay = Delay::from_saturating_duration(Duration::from_secs_f32(tick_seconds * ticks_per_frame as f32));
+        Ok(FrameRecorder { dir, ticks_per_frame, frames: 0, gif, delay })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn ticks_per_frame(&self) -> u32 {
+        self.ticks_per_frame
+    }
+
+    pub fn add_frame(&mut self, image: &RgbaImage) -> Result<(), String> {
+        self.frames += 1;
+        save_png(image, &self.dir.join(format!("frame_{:05}.png", self.frames)))?;
+        if let Some(gif) = self.gif.as_mut() {
+            gif.encode_frame(Frame::from_parts(image.clone(), 0, 0, self.delay))
+                .map_err(|e| format!("Failed to write {}: {}", self.dir.join("recording.gif").display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Closes the GIF, if any; returns the number of frames written.
+    pub fn finish(self) -> u32 {
+        self.frames
+    }
+}
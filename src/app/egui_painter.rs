@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::mem::{offset_of, size_of, size_of_val};
+
+use egui::epaint::{ImageDelta, Primitive, Vertex};
+use egui::{ClippedPrimitive, ImageData, TextureFilter, TextureId, TextureWrapMode, TexturesDelta};
+use gl::types::*;
+use nalgebra_glm as glm;
+
+use crate::shader::shaderprogram::{ShaderError, ShaderProgram};
+
+/// Draws egui's tessellated output with the `gl` bindings the rest of the
+/// renderer uses, on top of whatever is in the framebuffer.
+pub struct EguiPainter {
+    shader: ShaderProgram,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    textures: HashMap<TextureId, GLuint>,
+}
+
+impl EguiPainter {
+    pub fn new() -> Result<Self, ShaderError> {
+        let shader = ShaderProgram::new("assets/shaders/v_egui.glsl", None, "assets/shaders/f_egui.glsl")?;
+        let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            let stride = size_of::<Vertex>() as GLsizei;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, offset_of!(Vertex, pos) as *const _);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, offset_of!(Vertex, uv) as *const _);
+            gl::VertexAttribPointer(2, 4, gl::UNSIGNED_BYTE, gl::TRUE, stride, offset_of!(Vertex, color) as *const _);
+            for location in 0..3 {
+                gl::EnableVertexAttribArray(location);
+            }
+            gl::BindVertexArray(0);
+        }
+        Ok(EguiPainter { shader, vao, vbo, ebo, textures: HashMap::new() })
+    }
+
+    /// Rebuilds the shader if its files changed, see `Renderer::reload_shaders`.
+    pub fn reload_shader(&mut self) {
+        if self.shader.reload_if_changed() {
+            match self.shader.error() {
+                Some(err) => eprintln!("{}", err),
+                None => println!("Reloaded {}", self.shader.files()),
+            }
+        }
+    }
+
+    /// Largest texture side the driver accepts, for egui's font atlas.
+    pub fn max_texture_side() -> usize {
+        let mut side = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut side);
+        }
+        side as usize
+    }
+
+    /// Applies `textures_delta` and draws `primitives` over a framebuffer of
+    /// `size` pixels.
+    pub fn paint(
+        &mut self,
+        size: [u32; 2],
+        pixels_per_point: f32,
+        primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+    ) {
+        for (id, delta) in &textures_delta.set {
+            self.set_texture(*id, delta);
+        }
+
+        let [width, height] = size;
+        self.shader.use_program();
+        self.shader.set_vec2("screenSize", &glm::vec2(width as f32 / pixels_per_point, height as f32 / pixels_per_point));
+        self.shader.set_sampler("tex", 0);
+        unsafe {
+            gl::Viewport(0, 0, width as GLint, height as GLint);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE_MINUS_DST_ALPHA, gl::ONE);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+        }
+
+        for ClippedPrimitive { clip_rect, primitive } in primitives {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let Some(&texture) = self.textures.get(&mesh.texture_id) else {
+                continue;
+            };
+            // Clip rectangles are in points from the top left, scissor boxes
+            // in pixels from the bottom left.
+            let min_x = (clip_rect.min.x * pixels_per_point).round().clamp(0.0, width as f32);
+            let min_y = (clip_rect.min.y * pixels_per_point).round().clamp(0.0, height as f32);
+            let max_x = (clip_rect.max.x * pixels_per_point).round().clamp(min_x, width as f32);
+            let max_y = (clip_rect.max.y * pixels_per_point).round().clamp(min_y, height as f32);
+            if max_x <= min_x || max_y <= min_y {
+                continue;
+            }
+            unsafe {
+                gl::Scissor(
+                    min_x as GLint,
+                    height as GLint - max_y as GLint,
+                    (max_x - min_x) as GLsizei,
+                    (max_y - min_y) as GLsizei,
+                );
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    size_of_val(mesh.vertices.as_slice()) as GLsizeiptr,
+                    mesh.vertices.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+                gl::BufferData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    size_of_val(mesh.indices.as_slice()) as GLsizeiptr,
+                    mesh.indices.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+                gl::DrawElements(gl::TRIANGLES, mesh.indices.len() as GLsizei, gl::UNSIGNED_INT, std::ptr::null());
+            }
+        }
+
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+
+        for id in &textures_delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                unsafe {
+                    gl::DeleteTextures(1, &texture);
+                }
+            }
+        }
+    }
+
+    /// Creates or updates (part of) a texture.
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let pixels: Vec<egui::Color32> = match &delta.image {
+            ImageData::Color(image) => image.pixels.clone(),
+            ImageData::Font(image) => image.srgba_pixels(None).collect(),
+        };
+        let [width, height] = delta.image.size().map(|side| side as GLsizei);
+        let filter = |filter: TextureFilter| match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        } as GLint;
+        let wrap = match delta.options.wrap_mode {
+            TextureWrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrapMode::Repeat => gl::REPEAT,
+            TextureWrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+        } as GLint;
+
+        unsafe {
+            let texture = *self.textures.entry(id).or_insert_with(|| {
+                let mut texture = 0;
+                gl::GenTextures(1, &mut texture);
+                texture
+            });
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter(delta.options.magnification));
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter(delta.options.minification));
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap);
+            match delta.pos {
+                Some([x, y]) => gl::TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    x as GLint,
+                    y as GLint,
+                    width,
+                    height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                ),
+                None => gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as GLint,
+                    width,
+                    height,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                ),
+            }
+        }
+    }
+}
+
+impl Drop for EguiPainter {
+    fn drop(&mut self) {
+        unsafe {
+            for texture in self.textures.values() {
+                gl::DeleteTextures(1, texture);
+            }
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
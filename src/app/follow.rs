@@ -0,0 +1,108 @@
+use rand::seq::IndexedRandom;
+
+use cosmic_ant_sim::simulation::ant::Ant;
+use cosmic_ant_sim::simulation::caste::Caste;
+use cosmic_ant_sim::simulation::pheromone::Channel;
+use cosmic_ant_sim::simulation::world::World;
+
+/// Where the camera sits while following an ant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowView {
+    /// Behind and above the ant, looking past it.
+    Chase,
+    /// At the ant's head, looking where it goes.
+    FirstPerson,
+    /// Circling the ant: drag to rotate, wheel to zoom.
+    Orbit,
+}
+
+impl FollowView {
+    pub fn name(self) -> &'static str {
+        match self {
+            FollowView::Chase => "chase",
+            FollowView::FirstPerson => "first-person",
+            FollowView::Orbit => "orbit",
+        }
+    }
+
+    /// The view after this one, or `None` after the last to stop following.
+    pub fn next(self) -> Option<FollowView> {
+        match self {
+            FollowView::Chase => Some(FollowView::FirstPerson),
+            FollowView::FirstPerson => Some(FollowView::Orbit),
+            FollowView::Orbit => None,
+        }
+    }
+}
+
+pub fn find_ant(world: &World, id: u32) -> Option<&Ant> {
+    world.ants.iter().find(|ant| ant.id == id)
+}
+
+fn index_of(world: &World, id: u32) -> Option<usize> {
+    world.ants.iter().position(|ant| ant.id == id)
+}
+
+/// Id of the ant `step` places after `current` in spawn order, wrapping
+/// around; negative steps go back. Without a current ant, starts at either end.
+pub fn step_ant(world: &World, current: Option<u32>, step: isize) -> Option<u32> {
+    let count = world.ants.len() as isize;
+    if count == 0 {
+        return None;
+    }
+    let index = match current.and_then(|id| index_of(world, id)) {
+        Some(index) => (index as isize + step).rem_euclid(count),
+        None if step < 0 => count - 1,
+        None => 0,
+    };
+    Some(world.ants[index as usize].id)
+}
+
+/// Id of the first ant of `caste` after `current` in spawn order, wrapping around.
+pub fn next_of_caste(world: &World, current: Option<u32>, caste: Caste) -> Option<u32> {
+    let count = world.ants.len();
+    let start = current.and_then(|id| index_of(world, id)).map_or(0, |index| index + 1);
+    (0..count).map(|i| &world.ants[(start + i) % count]).find(|ant| ant.caste == caste).map(|ant| ant.id)
+}
+
+/// Id of an ant of the first caste after the one of `current` that has any ants.
+pub fn next_caste(world: &World, current: Option<u32>) -> Option<u32> {
+    let caste = current.and_then(|id| find_ant(world, id)).map_or(Caste::ALL.len() - 1, |ant| ant.caste.index());
+    (1..=Caste::ALL.len())
+        .map(|i| Caste::ALL[(caste + i) % Caste::ALL.len()])
+        .find_map(|caste| next_of_caste(world, current, caste))
+}
+
+/// Id of an ant picked at random. Doesn't touch the simulation's random
+/// streams, so runs stay reproducible.
+pub fn random_ant(world: &World) -> Option<u32> {
+    world.ants.choose(&mut rand::rng()).map(|ant| ant.id)
+}
+
+/// One line on what the ant is doing: its state, energy, load and the
+/// pheromone concentrations where it is. Channels it currently climbs the
+/// gradient of are marked with `*`.
+pub fn readout(world: &World, ant: &Ant) -> String {
+    let sensitivity = &world.castes().get(ant.caste).sensitivity;
+    let sensed: Vec<String> = Channel::ALL
+        .iter()
+        .map(|&channel| {
+            let followed = sensitivity.get(channel) > 0.0 && ant.state.attracted_to(channel);
+            format!(
+                "{} {:.3}{}",
+                channel.name(),
+                world.pheromones.sample(channel, &ant.position),
+                if followed { "*" } else { "" }
+            )
+        })
+        .collect();
+    format!(
+        "{} #{}: {}, energy {:.2}, carrying {:.2} | {}",
+        ant.caste.name(),
+        ant.id,
+        ant.state.name(),
+        ant.energy,
+        ant.carrying,
+        sensed.join(", ")
+    )
+}
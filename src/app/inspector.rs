@@ -0,0 +1,93 @@
+use nalgebra_glm as glm;
+
+use cosmic_ant_sim::simulation::ant::Ant;
+use cosmic_ant_sim::simulation::foraging::{FoodSource, ForageState, Nest};
+use cosmic_ant_sim::simulation::pheromone::Channel;
+use cosmic_ant_sim::simulation::world::World;
+
+use super::picking::Selection;
+
+/// Heading and `(label, value)` rows describing the whole state of one entity.
+pub struct Inspection {
+    pub heading: String,
+    pub rows: Vec<(&'static str, String)>,
+}
+
+impl Inspection {
+    /// The state of the selected entity, or `None` once it is gone.
+    pub fn of(world: &World, selection: Selection) -> Option<Self> {
+        match selection {
+            Selection::Ant(id) => world.ants.iter().find(|ant| ant.id == id).map(|ant| inspect_ant(world, ant)),
+            Selection::Food(id) => world.food_sources.iter().find(|food| food.id == id).map(inspect_food),
+            Selection::Nest(id) => world.nests.iter().find(|nest| nest.id == id).map(|nest| inspect_nest(world, nest)),
+        }
+    }
+}
+
+fn vector(v: &glm::Vec3) -> String {
+    format!("({:.2}, {:.2}, {:.2})", v.x, v.y, v.z)
+}
+
+fn optional_vector(v: &Option<glm::Vec3>) -> String {
+    v.as_ref().map_or("-".to_string(), vector)
+}
+
+fn inspect_ant(world: &World, ant: &Ant) -> Inspection {
+    let profile = world.castes().get(ant.caste);
+    let mut rows = vec![
+        ("caste", ant.caste.name().to_string()),
+        ("state", ant.state.name().to_string()),
+        ("energy", format!("{:.3}", ant.energy)),
+        ("age", format!("{:.1} of {:.0} s", ant.age, profile.lifespan)),
+        ("position", vector(&ant.position)),
+        ("velocity", vector(&ant.velocity)),
+        ("speed", format!("{:.2} of {:.2}", glm::length(&ant.velocity), profile.max_speed)),
+        ("forward", vector(&ant.forward())),
+        ("bank", format!("{:.1} deg", ant.bank.to_degrees())),
+        ("nest", ant.nest.map_or("-".to_string(), |nest| nest.to_string())),
+        ("carrying", format!("{:.2} of {:.2}", ant.carrying, profile.carrying_capacity)),
+        ("target", optional_vector(&ant.target)),
+        ("threat", optional_vector(&ant.threat)),
+        ("food memory", optional_vector(&ant.food_memory)),
+    ];
+    if let ForageState::Recruiting { remaining } = ant.state {
+        rows.push(("recruiting", format!("{:.1} s left", remaining)));
+    }
+    for channel in Channel::ALL {
+        rows.push((
+            channel.name(),
+            format!(
+                "{:.4} (sensitivity {:.2})",
+                world.pheromones.sample(channel, &ant.position),
+                profile.sensitivity.get(channel)
+            ),
+        ));
+    }
+    Inspection { heading: format!("Ant {}", ant.id), rows }
+}
+
+fn inspect_food(food: &FoodSource) -> Inspection {
+    Inspection {
+        heading: format!("Food source {}", food.id),
+        rows: vec![
+            ("position", vector(&food.position)),
+            ("radius", format!("{:.2}", food.radius)),
+            ("stock", format!("{:.2} of {:.2}", food.stock, food.capacity)),
+            ("regeneration", format!("{:.2} per s", food.regeneration_rate)),
+        ],
+    }
+}
+
+fn inspect_nest(world: &World, nest: &Nest) -> Inspection {
+    let residents = world.ants.iter().filter(|ant| ant.nest == Some(nest.id)).count();
+    Inspection {
+        heading: format!("Nest {}", nest.id),
+        rows: vec![
+            ("position", vector(&nest.position)),
+            ("radius", format!("{:.2}", nest.radius)),
+            ("stock", format!("{:.2}", nest.stock)),
+            ("collected", format!("{:.2}", nest.food_collected)),
+            ("ants", residents.to_string()),
+        ],
+    }
+}
@@ -1,3 +1,13 @@
 pub mod app_window;
+pub mod camera;
+pub mod capture;
+pub mod egui_painter;
+pub mod follow;
+pub mod inspector;
+pub mod pheromone_view;
+pub mod picking;
+pub mod plot;
+pub mod render_bench;
 pub mod renderer;
+pub mod ui;
 // pub mod teapot;
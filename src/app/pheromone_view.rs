@@ -0,0 +1,508 @@
+use std::mem::{size_of, size_of_val};
+
+use gl::types::*;
+use nalgebra_glm as glm;
+
+use crate::shader::shaderprogram::{ShaderError, ShaderProgram};
+use crate::utils::marching_cubes::march_cell;
+use cosmic_ant_sim::simulation::pheromone::{chunk_local_coord, local_index, Channel, ChannelGrid, CHUNK_SIZE};
+use cosmic_ant_sim::simulation::spatial::CellMap;
+use cosmic_ant_sim::simulation::world::World;
+
+/// Texels per voxel along each side of a slice.
+const SLICE_TEXELS_PER_VOXEL: f32 = 2.0;
+/// World units `[` and `]` move the slice by.
+const SLICE_STEP: f32 = 0.1;
+const MIN_THRESHOLD: f32 = 0.001;
+const THRESHOLD_STEP: f32 = 1.25;
+/// Point diameter as a fraction of the voxel size.
+const POINT_SCALE: f32 = 0.6;
+const SURFACE_OPACITY: f32 = 0.55;
+const AXIS_NAMES: [&str; 3] = ["x", "y", "z"];
+
+/// Colour of a concentration, from four evenly spaced stops going from none
+/// to the channel's peak.
+#[derive(Clone, Copy, Debug)]
+pub struct ColorMap(pub [glm::Vec3; 4]);
+
+impl ColorMap {
+    pub fn for_channel(channel: Channel) -> Self {
+        ColorMap(match channel {
+            // Dark green through yellow.
+            Channel::Trail => [
+                glm::vec3(0.0, 0.15, 0.05),
+                glm::vec3(0.0, 0.6, 0.2),
+                glm::vec3(0.5, 0.9, 0.1),
+                glm::vec3(1.0, 1.0, 0.6),
+            ],
+            // Embers to white heat.
+            Channel::Alarm => [
+                glm::vec3(0.2, 0.0, 0.0),
+                glm::vec3(0.7, 0.05, 0.0),
+                glm::vec3(1.0, 0.45, 0.0),
+                glm::vec3(1.0, 0.95, 0.8),
+            ],
+            // Purple to pink.
+            Channel::Recruitment => [
+                glm::vec3(0.15, 0.0, 0.2),
+                glm::vec3(0.5, 0.1, 0.6),
+                glm::vec3(0.9, 0.3, 0.8),
+                glm::vec3(1.0, 0.8, 1.0),
+            ],
+            // Deep blue to cyan.
+            Channel::Home => [
+                glm::vec3(0.0, 0.05, 0.2),
+                glm::vec3(0.0, 0.3, 0.7),
+                glm::vec3(0.1, 0.7, 0.9),
+                glm::vec3(0.8, 1.0, 1.0),
+            ],
+        })
+    }
+
+    /// Colour at `t` between 0 (no pheromone) and 1 (the peak). The shaders
+    /// have the same function as `colorAt`.
+    pub fn at(&self, t: f32) -> glm::Vec3 {
+        let t = t.clamp(0.0, 1.0) * 3.0;
+        let i = (t as usize).min(2);
+        glm::lerp(&self.0[i], &self.0[i + 1], t - i as f32)
+    }
+}
+
+/// What the pheromone passes show; compared against what was last built to
+/// tell when the GPU data is stale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewSettings {
+    /// Channels shown by every pass, indexed by `Channel`.
+    pub channels: [bool; Channel::ALL.len()],
+    pub slices: bool,
+    pub points: bool,
+    pub isosurface: bool,
+    /// Axis the slice is perpendicular to, 0-2 for x-z.
+    pub slice_axis: usize,
+    /// Where the slice cuts its axis, in world units.
+    pub slice_position: f32,
+    /// Fraction of each channel's peak below which points are hidden; the
+    /// isosurface is drawn at this level too.
+    pub threshold: f32,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ViewSettings {
+            channels: [true; Channel::ALL.len()],
+            slices: false,
+            points: false,
+            isosurface: false,
+            slice_axis: 2,
+            slice_position: 0.0,
+            threshold: 0.1,
+        }
+    }
+}
+
+/// A vertex array with one interleaved float buffer.
+struct VertexBuffer {
+    vao: GLuint,
+    vbo: GLuint,
+    vertex_count: i32,
+    floats_per_vertex: usize,
+}
+
+impl VertexBuffer {
+    /// `attributes` are the component counts of the attributes at locations 0, 1, ...
+    fn new(attributes: &[i32]) -> Self {
+        let floats_per_vertex = attributes.iter().sum::<i32>() as usize;
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            let stride = (floats_per_vertex * size_of::<f32>()) as GLsizei;
+            let mut offset = 0;
+            for (location, &size) in attributes.iter().enumerate() {
+                gl::VertexAttribPointer(location as GLuint, size, gl::FLOAT, gl::FALSE, stride, (offset * size_of::<f32>()) as *const _);
+                gl::EnableVertexAttribArray(location as GLuint);
+                offset += size as usize;
+            }
+            gl::BindVertexArray(0);
+        }
+        VertexBuffer { vao, vbo, vertex_count: 0, floats_per_vertex }
+    }
+
+    fn upload(&mut self, vertices: &[f32]) {
+        self.vertex_count = (vertices.len() / self.floats_per_vertex) as i32;
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, size_of_val(vertices) as GLsizeiptr, vertices.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+        }
+    }
+
+    fn draw(&self, mode: GLenum) {
+        if self.vertex_count > 0 {
+            unsafe {
+                gl::BindVertexArray(self.vao);
+                gl::DrawArrays(mode, 0, self.vertex_count);
+            }
+        }
+    }
+}
+
+impl Drop for VertexBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// GPU data of one channel.
+struct Layer {
+    peak: f32,
+    slice_texture: GLuint,
+    /// Position and normalised concentration of every voxel above the threshold.
+    points: VertexBuffer,
+    /// Position and normal of every isosurface vertex.
+    surface: VertexBuffer,
+}
+
+/// Renderer passes showing the pheromone field as axis-aligned slices, a point
+/// cloud of the voxels above a threshold and an isosurface at that threshold.
+/// Everything is rebuilt from the field when the world has moved on or the
+/// settings changed.
+pub struct PheromoneView {
+    pub settings: ViewSettings,
+    slice_shader: ShaderProgram,
+    point_shader: ShaderProgram,
+    surface_shader: ShaderProgram,
+    /// Unit square the slices are drawn with.
+    quad: VertexBuffer,
+    layers: Vec<Layer>,
+    /// Tick and settings the layers hold.
+    built: Option<(u64, ViewSettings)>,
+    /// Scratch buffers reused between rebuilds.
+    vertices: Vec<f32>,
+    texels: Vec<f32>,
+}
+
+impl PheromoneView {
+    pub fn new() -> Result<Self, ShaderError> {
+        let mut quad = VertexBuffer::new(&[2]);
+        quad.upload(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let layers = Channel::ALL
+            .map(|_| {
+                let mut slice_texture = 0;
+                unsafe {
+                    gl::GenTextures(1, &mut slice_texture);
+                    gl::BindTexture(gl::TEXTURE_2D, slice_texture);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                }
+                Layer { peak: 0.0, slice_texture, points: VertexBuffer::new(&[3, 1]), surface: VertexBuffer::new(&[3, 3]) }
+            })
+            .into();
+        Ok(PheromoneView {
+            settings: ViewSettings::default(),
+            slice_shader: ShaderProgram::new(
+                "assets/shaders/v_pheromone_slice.glsl",
+                None,
+                "assets/shaders/f_pheromone_slice.glsl",
+            )?,
+            point_shader: ShaderProgram::new(
+                "assets/shaders/v_pheromone_points.glsl",
+                None,
+                "assets/shaders/f_pheromone_points.glsl",
+            )?,
+            surface_shader: ShaderProgram::new(
+                "assets/shaders/v_pheromone_surface.glsl",
+                None,
+                "assets/shaders/f_pheromone_surface.glsl",
+            )?,
+            quad,
+            layers,
+            built: None,
+            vertices: Vec::new(),
+            texels: Vec::new(),
+        })
+    }
+
+    pub fn shaders(&self) -> [&ShaderProgram; 3] {
+        [&self.slice_shader, &self.point_shader, &self.surface_shader]
+    }
+
+    pub fn shaders_mut(&mut self) -> [&mut ShaderProgram; 3] {
+        [&mut self.slice_shader, &mut self.point_shader, &mut self.surface_shader]
+    }
+
+    /// Applies a view key, returning whether `key` was one:
+    /// 1-4 toggle the channels, x the slices, c the point cloud and m the
+    /// isosurface mesh; z picks the slice axis, [ and ] move the slice and
+    /// - and = lower and raise the threshold.
+    pub fn handle_key(&mut self, key: &str) -> bool {
+        let settings = &mut self.settings;
+        match key {
+            "1" | "2" | "3" | "4" => {
+                let channel = key.parse::<usize>().unwrap() - 1;
+                settings.channels[channel] = !settings.channels[channel];
+            },
+            "x" | "X" => settings.slices = !settings.slices,
+            "c" | "C" => settings.points = !settings.points,
+            "m" | "M" => settings.isosurface = !settings.isosurface,
+            "z" | "Z" => settings.slice_axis = (settings.slice_axis + 1) % 3,
+            "[" => settings.slice_position -= SLICE_STEP,
+            "]" => settings.slice_position += SLICE_STEP,
+            "-" => settings.threshold = (settings.threshold / THRESHOLD_STEP).max(MIN_THRESHOLD),
+            "=" | "+" => settings.threshold = (settings.threshold * THRESHOLD_STEP).min(1.0),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn describe(&self) -> String {
+        let settings = &self.settings;
+        let channels: Vec<&str> =
+            Channel::ALL.iter().filter(|channel| settings.channels[channel.index()]).map(|channel| channel.name()).collect();
+        let mut passes = Vec::new();
+        if settings.slices {
+            passes.push(format!("slice {} = {:.2}", AXIS_NAMES[settings.slice_axis], settings.slice_position));
+        }
+        if settings.points {
+            passes.push("point cloud".to_string());
+        }
+        if settings.isosurface {
+            passes.push("isosurface".to_string());
+        }
+        format!(
+            "Pheromones [{}]: {}, threshold {:.1}% of peak",
+            channels.join(" "),
+            if passes.is_empty() { "hidden".to_string() } else { passes.join(", ") },
+            settings.threshold * 100.0
+        )
+    }
+
+    /// Draws the enabled passes over the scene, rebuilding them first if the
+    /// field or the settings changed. Expects the frame's uniform block bound;
+    /// `projection` sizes the points.
+    pub fn draw(&mut self, world: &World, projection: &glm::Mat4) {
+        let settings = self.settings;
+        if !(settings.slices || settings.points || settings.isosurface) {
+            return;
+        }
+        self.settings.slice_position = settings.slice_position.clamp(-world.bounds_radius, world.bounds_radius);
+        if self.built != Some((world.tick, self.settings)) {
+            self.rebuild(world);
+            self.built = Some((world.tick, self.settings));
+        }
+        let settings = self.settings;
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::PROGRAM_POINT_SIZE);
+        }
+        for channel in Channel::ALL {
+            let layer = &self.layers[channel.index()];
+            if !settings.channels[channel.index()] || layer.peak <= 0.0 {
+                continue;
+            }
+            let colormap = ColorMap::for_channel(channel);
+
+            if settings.isosurface {
+                let shader = &self.surface_shader;
+                shader.use_program();
+                let color = colormap.at(0.75);
+                shader.set_vec4("color", &glm::vec4(color.x, color.y, color.z, SURFACE_OPACITY));
+                layer.surface.draw(gl::TRIANGLES);
+            }
+
+            if settings.points {
+                let shader = &self.point_shader;
+                shader.use_program();
+                let mut viewport = [0; 4];
+                unsafe {
+                    gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+                }
+                // Pixels across a point one world unit in front of the camera.
+                let point_size = 0.5 * viewport[3] as f32 * projection[(1, 1)] * POINT_SCALE * world.pheromones.voxel_size();
+                shader.set_vec3_array("colormap", &colormap.0);
+                shader.set_f32("pointSize", point_size);
+                layer.points.draw(gl::POINTS);
+            }
+
+            if settings.slices {
+                let shader = &self.slice_shader;
+                shader.use_program();
+                let plane = slice_plane(settings.slice_axis, settings.slice_position, world.bounds_radius);
+                shader.set_mat4("M", &plane);
+                shader.set_vec3_array("colormap", &colormap.0);
+                shader.set_sampler("slice", 0);
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE0);
+                    gl::BindTexture(gl::TEXTURE_2D, layer.slice_texture);
+                }
+                self.quad.draw(gl::TRIANGLE_STRIP);
+            }
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::Disable(gl::PROGRAM_POINT_SIZE);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    fn rebuild(&mut self, world: &World) {
+        let settings = self.settings;
+        let field = &world.pheromones;
+        let voxel_size = field.voxel_size();
+        for channel in Channel::ALL {
+            let layer = &mut self.layers[channel.index()];
+            let grid = field.channel(channel);
+            layer.peak = if settings.channels[channel.index()] { grid.peak() } else { 0.0 };
+            if layer.peak <= 0.0 {
+                continue;
+            }
+            let level = settings.threshold * layer.peak;
+
+            if settings.points {
+                self.vertices.clear();
+                for (key, voxels) in grid.chunks() {
+                    for (index, &value) in voxels.iter().enumerate() {
+                        if value >= level {
+                            let center = field.voxel_center(key * CHUNK_SIZE + chunk_local_coord(index));
+                            self.vertices.extend_from_slice(&[center.x, center.y, center.z, value / layer.peak]);
+                        }
+                    }
+                }
+                layer.points.upload(&self.vertices);
+            }
+
+            if settings.isosurface {
+                self.vertices.clear();
+                build_isosurface(grid, voxel_size, level, &mut self.vertices);
+                layer.surface.upload(&self.vertices);
+            }
+
+            if settings.slices {
+                let size = (2.0 * world.bounds_radius / voxel_size * SLICE_TEXELS_PER_VOXEL).ceil().max(1.0) as usize;
+                let plane = slice_plane(settings.slice_axis, settings.slice_position, world.bounds_radius);
+                self.texels.clear();
+                for row in 0..size {
+                    for column in 0..size {
+                        let uv = glm::vec4((column as f32 + 0.5) / size as f32, (row as f32 + 0.5) / size as f32, 0.0, 1.0);
+                        let position = (plane * uv).xyz();
+                        self.texels.push(field.sample(channel, &position) / layer.peak);
+                    }
+                }
+                unsafe {
+                    gl::BindTexture(gl::TEXTURE_2D, layer.slice_texture);
+                    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::R32F as GLint,
+                        size as GLsizei,
+                        size as GLsizei,
+                        0,
+                        gl::RED,
+                        gl::FLOAT,
+                        self.texels.as_ptr() as *const _,
+                    );
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PheromoneView {
+    fn drop(&mut self) {
+        for layer in &self.layers {
+            unsafe {
+                gl::DeleteTextures(1, &layer.slice_texture);
+            }
+        }
+    }
+}
+
+/// Maps the unit square onto the slice: the square of side `2 * bounds_radius`
+/// perpendicular to `axis`, centred on it at `position`.
+fn slice_plane(axis: usize, position: f32, bounds_radius: f32) -> glm::Mat4 {
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let unit = |a: usize| glm::Vec3::from_fn(|i, _| if i == a { 1.0 } else { 0.0 });
+    let side = 2.0 * bounds_radius;
+    let origin = unit(axis) * position - (unit(u) + unit(v)) * bounds_radius;
+    let mut plane = glm::Mat4::identity();
+    plane.set_column(0, &(unit(u) * side).push(0.0));
+    plane.set_column(1, &(unit(v) * side).push(0.0));
+    plane.set_column(2, &unit(axis).push(0.0));
+    plane.set_column(3, &origin.push(1.0));
+    plane
+}
+
+/// Appends the marching-cubes isosurface of `grid` at `level` as triangles of
+/// interleaved world positions and face normals. The cells have voxel centres
+/// as corners, and normals point toward lower concentration.
+fn build_isosurface(grid: &ChannelGrid, voxel_size: f32, level: f32, out: &mut Vec<f32>) {
+    let offset = |d: usize| glm::vec3((d & 1) as i32, ((d >> 1) & 1) as i32, ((d >> 2) & 1) as i32);
+    // A cell reaches one voxel into the chunks on its positive sides, so
+    // chunks just below allocated ones can hold surface too.
+    let mut keys: CellMap<()> = CellMap::default();
+    for (key, _) in grid.chunks() {
+        for d in 0..8 {
+            keys.insert(key - offset(d), ());
+        }
+    }
+
+    const SIDE: usize = CHUNK_SIZE as usize + 1;
+    let mut block = [0.0f32; SIDE * SIDE * SIDE];
+    let at = |x: usize, y: usize, z: usize| x + SIDE * (y + SIDE * z);
+    for key in keys.keys() {
+        // The chunk's voxels and the first layer of its positive neighbours.
+        let chunks: [Option<&[f32]>; 8] = std::array::from_fn(|d| grid.chunk(key + offset(d)));
+        let mut peak: f32 = 0.0;
+        for z in 0..SIDE {
+            for y in 0..SIDE {
+                for x in 0..SIDE {
+                    let local = glm::vec3(x as i32, y as i32, z as i32);
+                    let d = (x / CHUNK_SIZE as usize) | (y / CHUNK_SIZE as usize) << 1 | (z / CHUNK_SIZE as usize) << 2;
+                    let value = chunks[d].map_or(0.0, |chunk| chunk[local_index(local.map(|c| c % CHUNK_SIZE))]);
+                    block[at(x, y, z)] = value;
+                    peak = peak.max(value);
+                }
+            }
+        }
+        if peak < level {
+            continue;
+        }
+
+        let base = key * CHUNK_SIZE;
+        for z in 0..SIDE - 1 {
+            for y in 0..SIDE - 1 {
+                for x in 0..SIDE - 1 {
+                    let values = std::array::from_fn(|i| block[at(x + (i & 1), y + ((i >> 1) & 1), z + ((i >> 2) & 1))]);
+                    // Cell-local to world: voxel v's centre is at (v + 0.5) * voxel_size.
+                    let cell = glm::convert::<glm::IVec3, glm::Vec3>(base + glm::vec3(x as i32, y as i32, z as i32))
+                        + glm::vec3(0.5, 0.5, 0.5);
+                    march_cell(&values, level, |triangle| {
+                        let [a, b, c] = triangle.map(|p| (cell + p) * voxel_size);
+                        let normal = glm::cross(&(b - a), &(c - a));
+                        // Corners right at the level collapse triangles to nothing.
+                        if normal == glm::Vec3::zeros() {
+                            return;
+                        }
+                        let normal = glm::normalize(&normal);
+                        for p in [a, b, c] {
+                            out.extend_from_slice(&[p.x, p.y, p.z, normal.x, normal.y, normal.z]);
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use nalgebra_glm as glm;
+
+use cosmic_ant_sim::simulation::caste::Caste;
+use cosmic_ant_sim::simulation::spatial::{ray_sphere, SpatialGrid};
+use cosmic_ant_sim::simulation::world::World;
+
+use super::renderer::{caste_appearance, food_radius};
+
+/// Radius of an ant's bounding sphere, in lengths of its mesh.
+const ANT_PICK_RADIUS: f32 = 0.6;
+/// Picking ignores anything further away than this.
+const MAX_PICK_DISTANCE: f32 = 100.0;
+
+/// A ray with a normalised direction.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: glm::Vec3,
+    pub direction: glm::Vec3,
+}
+
+/// Something picked in the 3D view, by id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    Ant(u32),
+    Food(u32),
+    Nest(u32),
+}
+
+/// Bounding sphere radius of an ant of `caste`.
+pub fn ant_radius(caste: Caste) -> f32 {
+    caste_appearance(caste).scale * ANT_PICK_RADIUS
+}
+
+/// The entity `ray` hits first. Ants are small and often inside a nest or in
+/// front of a food source, so any ant hit wins over food and nests.
+pub fn pick(world: &World, ray: &Ray) -> Option<Selection> {
+    pick_ant(world, ray).map(Selection::Ant).or_else(|| {
+        let food = world
+            .food_sources
+            .iter()
+            .filter_map(|food| Some((ray_sphere(&ray.origin, &ray.direction, &food.position, food_radius(food))?, Selection::Food(food.id))));
+        let nests = world
+            .nests
+            .iter()
+            .filter_map(|nest| Some((ray_sphere(&ray.origin, &ray.direction, &nest.position, nest.radius)?, Selection::Nest(nest.id))));
+        food.chain(nests)
+            .filter(|(distance, _)| *distance <= MAX_PICK_DISTANCE)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, selection)| selection)
+    })
+}
+
+/// Id of the first ant hit by `ray`. The world's own spatial grid holds the
+/// positions of the start of the last step, so this builds one per caste from
+/// the current positions, each raycast with that caste's bounding radius.
+fn pick_ant(world: &World, ray: &Ray) -> Option<u32> {
+    let cell_size = world.spatial().cell_size();
+    let mut grid = SpatialGrid::new(cell_size);
+    let mut members = Vec::new();
+    let mut best: Option<(u32, f32)> = None;
+    for caste in Caste::ALL {
+        members.clear();
+        members.extend(world.ants.iter().filter(|ant| ant.caste == caste));
+        if members.is_empty() {
+            continue;
+        }
+        grid.rebuild(members.iter().map(|ant| ant.position));
+        let hit = grid.raycast(&ray.origin, &ray.direction, MAX_PICK_DISTANCE, ant_radius(caste));
+        if let Some((index, distance)) = hit.filter(|(_, distance)| best.is_none_or(|(_, b)| *distance < b)) {
+            best = Some((members[index].id, distance));
+        }
+    }
+    best.map(|(id, _)| id)
+}
@@ -0,0 +1,129 @@
+use egui::{Align2, Color32, FontId, Pos2, Sense, Stroke, StrokeKind, Ui};
+
+const PLOT_HEIGHT: f32 = 90.0;
+/// Fewest ticks a plot can be zoomed in to.
+const MIN_SPAN: f64 = 30.0;
+/// Zoom factor per point scrolled.
+const ZOOM_PER_POINT: f64 = 0.003;
+
+/// One line of a plot, a value per sample.
+pub struct Series {
+    pub name: String,
+    pub color: Color32,
+    pub values: Vec<f32>,
+}
+
+/// The tick range the plots show, shared so they all zoom and pan together.
+/// Scrolling zooms around the cursor, dragging pans and a double click goes
+/// back to showing everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlotView {
+    /// Ticks shown, or all of them.
+    span: Option<f64>,
+    /// Last tick shown, or the latest sample.
+    end: Option<f64>,
+}
+
+impl PlotView {
+    fn range(&self, first: f64, last: f64) -> (f64, f64) {
+        let span = self.span.unwrap_or(last - first).max(MIN_SPAN);
+        let end = self.end.unwrap_or(last);
+        (end - span, end)
+    }
+
+    /// Shows `[start, end]` of the samples from `first` to `last`, following
+    /// the latest again once the end reaches it.
+    fn set_range(&mut self, start: f64, end: f64, first: f64, last: f64) {
+        let span = (end - start).clamp(MIN_SPAN, (last - first).max(MIN_SPAN));
+        let end = (start + span).clamp(first + span, last.max(first + span));
+        self.span = if span >= last - first { None } else { Some(span) };
+        self.end = if end >= last { None } else { Some(end) };
+    }
+}
+
+/// Draws `series` against `ticks` over the range of `view`, with the value
+/// of each at the hovered tick in a tooltip. Updates `view` on input.
+pub fn time_series(ui: &mut Ui, view: &mut PlotView, ticks: &[f64], series: &[Series]) {
+    ui.horizontal_wrapped(|ui| {
+        for line in series {
+            ui.colored_label(line.color, &line.name);
+        }
+    });
+    let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), PLOT_HEIGHT), Sense::click_and_drag());
+    let rect = response.rect;
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    painter.rect_stroke(rect, 2.0, visuals.widgets.noninteractive.bg_stroke, StrokeKind::Inside);
+    let text_color = visuals.weak_text_color();
+    let font = FontId::monospace(10.0);
+
+    let (Some(&first), Some(&last)) = (ticks.first(), ticks.last()) else {
+        painter.text(rect.center(), Align2::CENTER_CENTER, "No samples yet", font, text_color);
+        return;
+    };
+
+    let (mut start, mut end) = view.range(first, last);
+    let tick_at = |x: f32, start: f64, end: f64| start + (x - rect.left()) as f64 / rect.width() as f64 * (end - start);
+    if response.double_clicked() {
+        *view = PlotView::default();
+        (start, end) = view.range(first, last);
+    } else if response.dragged() {
+        let shift = -response.drag_delta().x as f64 / rect.width() as f64 * (end - start);
+        view.set_range(start + shift, end + shift, first, last);
+        (start, end) = view.range(first, last);
+    } else if let Some(pointer) = response.hover_pos() {
+        let scroll = ui.input(|input| input.smooth_scroll_delta.y) as f64;
+        if scroll != 0.0 {
+            let factor = (-scroll * ZOOM_PER_POINT).exp();
+            let anchor = tick_at(pointer.x, start, end);
+            view.set_range(anchor - (anchor - start) * factor, anchor + (end - anchor) * factor, first, last);
+            (start, end) = view.range(first, last);
+        }
+    }
+
+    // Samples in range, plus one either side so lines reach the edges.
+    let from = ticks.partition_point(|&tick| tick < start).saturating_sub(1);
+    let to = (ticks.partition_point(|&tick| tick <= end) + 1).min(ticks.len());
+    let (mut min, mut max) = (0.0f32, f32::MIN);
+    for line in series {
+        for &value in &line.values[from..to] {
+            min = min.min(value);
+            max = max.max(value);
+        }
+    }
+    if max <= min {
+        max = min + 1.0;
+    }
+    max += (max - min) * 0.05;
+
+    let x = |tick: f64| rect.left() + ((tick - start) / (end - start)) as f32 * rect.width();
+    let y = |value: f32| rect.bottom() - (value - min) / (max - min) * rect.height();
+    let clipped = painter.with_clip_rect(rect);
+    for line in series {
+        let points: Vec<Pos2> = (from..to).map(|i| Pos2::new(x(ticks[i]), y(line.values[i]))).collect();
+        clipped.line(points, Stroke::new(1.5, line.color));
+    }
+    painter.text(rect.left_top() + egui::vec2(3.0, 2.0), Align2::LEFT_TOP, format_value(max), font.clone(), text_color);
+    painter.text(rect.left_bottom() + egui::vec2(3.0, -2.0), Align2::LEFT_BOTTOM, format_value(min), font.clone(), text_color);
+    let range = format!("ticks {:.0} to {:.0}", start.max(first), end);
+    painter.text(rect.right_bottom() + egui::vec2(-3.0, -2.0), Align2::RIGHT_BOTTOM, range, font, text_color);
+
+    let Some(pointer) = response.hover_pos() else {
+        return;
+    };
+    let hovered = tick_at(pointer.x, start, end);
+    let next = ticks.partition_point(|&tick| tick < hovered).min(ticks.len() - 1);
+    let nearest = if next > 0 && hovered - ticks[next - 1] < ticks[next] - hovered { next - 1 } else { next };
+    let marker = x(ticks[nearest]);
+    clipped.line_segment([Pos2::new(marker, rect.top()), Pos2::new(marker, rect.bottom())], Stroke::new(1.0, text_color));
+    response.on_hover_ui_at_pointer(|ui| {
+        ui.label(format!("tick {:.0}", ticks[nearest]));
+        for line in series {
+            ui.colored_label(line.color, format!("{}: {}", line.name, format_value(line.values[nearest])));
+        }
+    });
+}
+
+fn format_value(value: f32) -> String {
+    if value.abs() >= 100.0 || value == value.round() { format!("{:.0}", value) } else { format!("{:.3}", value) }
+}
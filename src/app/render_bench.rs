@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use nalgebra_glm as glm;
+
+use cosmic_ant_sim::simulation::caste::Caste;
+use cosmic_ant_sim::simulation::world::World;
+
+/// Swarm sizes `--render-bench` measures, each drawn per object and instanced.
+pub const BENCH_ANTS: [usize; 3] = [100, 10_000, 100_000];
+const BENCH_BOUNDS_RADIUS: f32 = 3.0;
+const BENCH_NEST_RADIUS: f32 = 0.4;
+const BENCH_FOOD_SOURCES: usize = 10;
+/// Frames drawn before measuring each case, to let drivers settle.
+const WARMUP_FRAMES: u32 = 30;
+const MEASURED_FRAMES: u32 = 300;
+/// Cases stop early once they have been measured for this long.
+const MAX_MEASURED_TIME: Duration = Duration::from_secs(5);
+
+/// Draws still swarms of `BENCH_ANTS` sizes through both drawing paths of the
+/// renderer and reports the mean frame time of each.
+pub struct RenderBench {
+    seed: u64,
+    /// Index of the running case; case `i` draws `BENCH_ANTS[i / 2]` ants,
+    /// instanced when `i` is odd.
+    case: usize,
+    world: World,
+    frame: u32,
+    measured: Duration,
+    /// Mean frame time of every finished case.
+    results: Vec<Duration>,
+}
+
+impl RenderBench {
+    pub fn new(seed: u64) -> Self {
+        RenderBench {
+            seed,
+            case: 0,
+            world: swarm(BENCH_ANTS[0], seed),
+            frame: 0,
+            measured: Duration::ZERO,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Whether the running case draws instanced.
+    pub fn instanced(&self) -> bool {
+        self.case % 2 == 1
+    }
+
+    /// Records how long a frame of the running case took, from the start of
+    /// drawing until the GPU finished it. Returns false once every case is done.
+    pub fn record(&mut self, frame_time: Duration) -> bool {
+        self.frame += 1;
+        if self.frame <= WARMUP_FRAMES {
+            return true;
+        }
+        self.measured += frame_time;
+        let measured_frames = self.frame - WARMUP_FRAMES;
+        if measured_frames < MEASURED_FRAMES && self.measured < MAX_MEASURED_TIME {
+            return true;
+        }
+
+        let mean = self.measured / measured_frames;
+        eprintln!(
+            "{} ants {}: {:.2} ms over {} frames",
+            self.world.ants.len(),
+            if self.instanced() { "instanced" } else { "per object" },
+            mean.as_secs_f64() * 1000.0,
+            measured_frames
+        );
+        self.results.push(mean);
+        self.case += 1;
+        self.frame = 0;
+        self.measured = Duration::ZERO;
+        if self.case == 2 * BENCH_ANTS.len() {
+            return false;
+        }
+        if self.case.is_multiple_of(2) {
+            self.world = swarm(BENCH_ANTS[self.case / 2], self.seed);
+        }
+        true
+    }
+
+    /// Table of the mean frame times of the finished cases.
+    pub fn report(&self) -> String {
+        let mut report = format!("{:>8} {:>12} {:>12} {:>9}\n", "ants", "per object", "instanced", "speed-up");
+        for (ants, times) in BENCH_ANTS.iter().zip(self.results.chunks_exact(2)) {
+            let (per_object, instanced) = (times[0].as_secs_f64(), times[1].as_secs_f64());
+            report += &format!(
+                "{:>8} {:>9.2} ms {:>9.2} ms {:>8.1}x\n",
+                ants,
+                per_object * 1000.0,
+                instanced * 1000.0,
+                per_object / instanced.max(1e-9)
+            );
+        }
+        report
+    }
+}
+
+/// A world of `ants` ants, split evenly between the castes.
+fn swarm(ants: usize, seed: u64) -> World {
+    let mut world = World::new(BENCH_BOUNDS_RADIUS, seed);
+    world.add_nest(glm::vec3(0.0, 0.0, 0.0), BENCH_NEST_RADIUS);
+    for (i, caste) in Caste::ALL.into_iter().enumerate() {
+        let count = ants / Caste::ALL.len() + usize::from(i < ants % Caste::ALL.len());
+        world.populate(caste, count);
+    }
+    world.respawn_food_sources(BENCH_FOOD_SOURCES);
+    world
+}
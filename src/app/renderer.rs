@@ -1,54 +1,119 @@
 use glutin::prelude::GlDisplay;
-use rand::{rng, thread_rng, Rng};
 use gl::types::*;
 use std::collections::HashMap;
-use std::ffi::{CStr, CString};
+use std::ffi::CString;
 use std::path::Path;
-use std::time::Instant;
 
 use image::GenericImageView;
 use nalgebra_glm as glm;
-use crate::shader::shaderprogram::ShaderProgram;
-use crate::models::{cube::Cube, model::*, shuttlebug::Shuttlebug, sphere::Sphere};
+use super::camera::Camera;
+use super::pheromone_view::PheromoneView;
+use super::picking::{ant_radius, Selection};
+use crate::shader::shaderprogram::{ShaderError, ShaderProgram};
+use crate::shader::uniform_block::{FrameBlock, Light, MaterialBlock, UniformBuffer};
+use crate::models::{cube::Cube, instances::{Instance, InstanceBuffer}, mesh::Mesh, model::*, shuttlebug::Shuttlebug, sphere::Sphere};
+use crate::simulation::caste::Caste;
+use crate::simulation::foraging::{FoodSource, Nest};
+use crate::simulation::world::World;
+
+const SPHERE_RADIUS: f32 = 0.3;
+const FOOD_COLOR: glm::Vec4 = glm::Vec4::new(1.0, 1.0, 1.0, 1.0);
+const NEST_COLOR: glm::Vec4 = glm::Vec4::new(0.55, 0.35, 0.2, 1.0);
+/// Tint of the selected ant and colour of the outline around any selection.
+const SELECTION_COLOR: glm::Vec4 = glm::Vec4::new(1.0, 0.25, 1.0, 1.0);
+/// Outline radius relative to the selected entity's bounding sphere.
+const SELECTION_MARGIN: f32 = 1.3;
+
+/// The lights the scene starts with, in camera space: one just behind the
+/// viewer and a sun shining down the view direction.
+fn default_lights() -> Vec<Light> {
+    vec![
+        Light::point(glm::vec3(0.0, 0.0, 2.0), glm::vec3(1.0, 1.0, 1.0)),
+        Light::directional(glm::vec3(0.5, 0.0, -1.0), glm::vec3(1.0, 1.0, 1.0)),
+    ]
+}
+
+/// How a caste is drawn: which model, at what scale and with which tint.
+pub struct CasteAppearance {
+    pub model: &'static str,
+    pub scale: f32,
+    pub color: glm::Vec4,
+}
+
+pub fn caste_appearance(caste: Caste) -> CasteAppearance {
+    match caste {
+        Caste::Worker => CasteAppearance { model: "ant", scale: 0.2, color: glm::vec4(1.0, 1.0, 1.0, 1.0) },
+        Caste::Forager => CasteAppearance { model: "ant", scale: 0.2, color: glm::vec4(0.6, 1.0, 0.6, 1.0) },
+        Caste::Soldier => CasteAppearance { model: "ant", scale: 0.28, color: glm::vec4(1.0, 0.5, 0.4, 1.0) },
+        Caste::Queen => CasteAppearance { model: "ant", scale: 0.45, color: glm::vec4(1.0, 0.85, 0.3, 1.0) },
+        Caste::Scout => CasteAppearance { model: "ant", scale: 0.15, color: glm::vec4(0.5, 0.7, 1.0, 1.0) },
+    }
+}
+
+/// Which parts of the scene are drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Passes {
+    pub ants: bool,
+    pub food: bool,
+    pub nests: bool,
+    pub selection: bool,
+    pub pheromones: bool,
+}
+
+impl Default for Passes {
+    fn default() -> Self {
+        Passes { ants: true, food: true, nests: true, selection: true, pheromones: true }
+    }
+}
 
 pub struct Renderer {
-    M: glm::Mat4,
-    V: glm::Mat4,
-    P: glm::Mat4,
     shader: ShaderProgram,
     lambert: ShaderProgram,
+    colored: ShaderProgram,
     models: HashMap<String, Box<dyn Model>>,
-    pub zoom: f32,
-    dirtTexture: GLuint,
-    random_pos_vector: Vec<glm::Vec3>, 
-    pub speed: f32
-
+    dirt_texture: GLuint,
+    /// Draw every model once per frame with all its copies in one call,
+    /// rather than one call per object.
+    pub instancing: bool,
+    /// Draw ants and food as wireframes.
+    pub wireframe: bool,
+    pub passes: Passes,
+    shader_instanced: ShaderProgram,
+    lambert_instanced: ShaderProgram,
+    colored_instanced: ShaderProgram,
+    instance_buffer: InstanceBuffer,
+    /// Ant instances of the frame, by model name.
+    ant_batches: HashMap<&'static str, Vec<Instance>>,
+    instances: Vec<Instance>,
+    pub pheromones: PheromoneView,
+    /// Tinted and outlined while it exists.
+    pub selection: Option<Selection>,
+    /// Lights of the textured models, up to `MAX_LIGHTS`.
+    pub lights: Vec<Light>,
+    frame: UniformBuffer<FrameBlock>,
+    ant_material: UniformBuffer<MaterialBlock>,
 }
 
 impl Renderer {
-    pub fn new<D: GlDisplay>(gl_display: &D) -> Self {
+    pub fn new<D: GlDisplay>(gl_display: &D) -> Result<Self, ShaderError> {
         gl::load_with(|symbol| {
             let symbol = CString::new(symbol).unwrap();
             gl_display.get_proc_address(symbol.as_c_str()).cast()
         });
 
-        let spLambert = ShaderProgram::new(
+        let sp_lambert = ShaderProgram::new(
             "assets/shaders/v_lambert.glsl", 
             None,
             "assets/shaders/f_lambert.glsl",
-        );
-        let spConstant = ShaderProgram::new(
-            "assets/shaders/v_constant.glsl", 
-            None,
-            "assets/shaders/f_constant.glsl",
-        );
-        let spSimple = ShaderProgram::new(
+        )?;
+        let sp_simple = ShaderProgram::with_defines(
             "assets/shaders/v_simple.glsl", 
             None,
             "assets/shaders/f_simple.glsl",
-        );
+            &["HAS_NORMAL_MAP"],
+        )?;
 
-        let mut models = HashMap::new();
+        let models = HashMap::new();
         
         // let spColored = ShaderProgram::new(
         //     "assets/shaders/v_colored.glsl", 
@@ -60,52 +125,98 @@ impl Renderer {
         //     None,
         //     "assets/shaders/f_textured.glsl",
         // );
-        let spLambertTextured = ShaderProgram::new(
+        let sp_lambert_textured = ShaderProgram::new(
             "assets/shaders/v_lamberttextured.glsl", 
             None,
             "assets/shaders/f_lamberttextured.glsl",
-        );
-        let r:Option<f32> = Some(0.3);
-        let mainDivs:Option<f32> = Some(36.0);
-        let tubeDivs:Option<f32> = Some(36.0);
-
-        let mut fov: f32 = glm::radians(&glm::vec1(100.0)).x;
-        let aspect = 1900.0 / 1100.0;
-        let mut P: glm::Mat4 = glm::perspective(aspect,fov,1.0,50.0);
-        let mut eye = glm::vec3(0.0 ,0.0, -5.0);
-        let mut center = glm::vec3(0.0, 0.0, 0.0);
-        let mut up = glm::vec3(0.0, 1.0, 0.0);
-        let mut V: glm::Mat4 = glm::look_at(&eye, &center, &up);
-        // V = glm::rotate(&V, 0.5*PI, &glm::vec3(0.0,1.0,0.0));
-        // let mut M: glm::Mat4 = glm::Mat4::from_element(1.0);
-
-        let mut M = glm::identity();
-
-        // M = glm::scale(&M, &glm::vec3(5.0,5.0,5.0));
-
-        let mut mySphere = Box::new(Sphere::new(r, mainDivs, tubeDivs));
-        let mut myCube = Box::new(Cube::new());
-        let mut myShuttlebug  = Box::new(Shuttlebug::new());
-        let mut renderer = Renderer {M,V,P,shader: spSimple, lambert: spLambertTextured, models, zoom: 5.0, dirtTexture: 0, speed: 0.0, random_pos_vector: Vec::new()};
-        renderer.generateRandomPos();
-        renderer.dirtTexture = renderer.load_texture("assets/textures/dirtTexture.png");
+        )?;
+        let sp_simple_instanced = ShaderProgram::with_defines(
+            "assets/shaders/v_simple.glsl",
+            None,
+            "assets/shaders/f_simple.glsl",
+            &["INSTANCED", "HAS_NORMAL_MAP"],
+        )?;
+        let sp_lambert_textured_instanced = ShaderProgram::with_defines(
+            "assets/shaders/v_lamberttextured.glsl",
+            None,
+            "assets/shaders/f_lamberttextured.glsl",
+            &["INSTANCED"],
+        )?;
+        let sp_lambert_instanced = ShaderProgram::with_defines(
+            "assets/shaders/v_lambert.glsl",
+            None,
+            "assets/shaders/f_lambert.glsl",
+            &["INSTANCED"],
+        )?;
+        let r:Option<f32> = Some(SPHERE_RADIUS);
+        let main_divs:Option<f32> = Some(36.0);
+        let tube_divs:Option<f32> = Some(36.0);
+
+        let sphere = Box::new(Sphere::new(r, main_divs, tube_divs));
+        let cube = Box::new(Cube::new());
+        let shuttlebug = Box::new(Shuttlebug::new());
+        let mut renderer = Renderer {
+            shader: sp_simple, lambert: sp_lambert_textured, colored: sp_lambert, models, dirt_texture: 0,
+            instancing: true,
+            wireframe: false,
+            passes: Passes::default(),
+            shader_instanced: sp_simple_instanced,
+            lambert_instanced: sp_lambert_textured_instanced,
+            colored_instanced: sp_lambert_instanced,
+            instance_buffer: InstanceBuffer::new(),
+            ant_batches: HashMap::new(),
+            instances: Vec::new(),
+            pheromones: PheromoneView::new()?,
+            selection: None,
+            lights: default_lights(),
+            // Filled from the camera on every draw.
+            frame: UniformBuffer::new(&FrameBlock::new(glm::identity(), glm::identity(), 0.0, &[])),
+            ant_material: UniformBuffer::new(&MaterialBlock::new(0.2, 0.5, 16.0)),
+        };
+        renderer.dirt_texture = renderer.load_texture("assets/textures/dirtTexture.png");
         
            
-        renderer.addModel("cube", myCube);
-        renderer.addModel("sphere",mySphere);
-        renderer.addModel("ant",myShuttlebug);
-        renderer
+        renderer.add_model("cube", cube);
+        renderer.add_model("sphere", sphere);
+        renderer.add_model("ant", shuttlebug);
+        Ok(renderer)
     }
 
-    pub fn changeCameraZoom(&mut self) {
-        let mut eye = glm::vec3(0.0 ,0.0, -self.zoom);
-        let mut center = glm::vec3(0.0, 0.0, 0.0);
-        let mut up = glm::vec3(0.0, 1.0, 0.0);
-        self.V = glm::look_at(&eye, &center, &up);
-        
+    fn shaders_mut(&mut self) -> impl Iterator<Item = &mut ShaderProgram> {
+        [
+            &mut self.shader,
+            &mut self.lambert,
+            &mut self.colored,
+            &mut self.shader_instanced,
+            &mut self.lambert_instanced,
+            &mut self.colored_instanced,
+        ]
+        .into_iter()
+        .chain(self.pheromones.shaders_mut())
+    }
+
+    /// Rebuilds every program whose GLSL files changed on disk. Programs that
+    /// fail to build keep drawing with their previous version; their errors
+    /// are printed here and listed by `shader_errors` until fixed.
+    pub fn reload_shaders(&mut self) {
+        for shader in self.shaders_mut() {
+            if shader.reload_if_changed() {
+                match shader.error() {
+                    Some(err) => eprintln!("{}", err),
+                    None => println!("Reloaded {}", shader.files()),
+                }
+            }
+        }
+    }
+
+    pub fn shader_errors(&self) -> Vec<&ShaderError> {
+        [&self.shader, &self.lambert, &self.colored, &self.shader_instanced, &self.lambert_instanced, &self.colored_instanced]
+            .into_iter()
+            .chain(self.pheromones.shaders())
+            .filter_map(ShaderProgram::error)
+            .collect()
     }
 
-    
     fn load_texture<P: AsRef<Path>>(&self, path: P) -> GLuint {
         // Load the image using the image crate
         let img = image::open(path).expect("Failed to load texture");
@@ -146,34 +257,19 @@ impl Renderer {
 
         
 
-    pub fn addModel(&mut self, name: impl Into<String>, mut model: Box<dyn Model>) {
+    pub fn add_model(&mut self, name: impl Into<String>, model: Box<dyn Model>) {
         self.models.insert(name.into(), model);
     }
 
-    pub fn generateRandomPos(&mut self) {
-        self.random_pos_vector.clear();
-        for i in 0..10 {
-            self.random_pos_vector.push(self.spherical_rand(3.0));
-        }
-    }
-
-    fn spherical_rand(&self,radius: f32) -> glm::Vec3 {
-        let mut rng = rng();
-
-        let u: f32 = rng.random_range(0.0..1.0);
-        let v: f32 = rng.random_range(0.0..1.0);
-
-        let theta = 2.0 * std::f32::consts::PI * u;
-        let phi = (1.0 - 2.0 * v).acos();
-
-        let x = radius * phi.sin() * theta.cos();
-        let y = radius * phi.sin() * theta.sin();
-        let z = radius * phi.cos();
-
-        glm::vec3(x, y, z)
+    /// Geometry of the model added as `name`.
+    pub fn mesh(&self, name: &str) -> Option<&Mesh> {
+        self.models.get(name).map(|model| model.mesh())
     }
 
-    pub fn draw(&mut self) {
+    pub fn draw(&mut self, world: &World, camera: &Camera) {
+        let projection = camera.projection();
+        self.frame.update(&FrameBlock::new(projection, camera.view(), world.time as f32, &self.lights));
+        self.frame.bind();
 
         // let V = Matrix4::look_at_rh(&Point3::new(1.5, 1.5, 2.0), &Point3::origin(), &Vector3::y());
         // let Pp = Perspective3::new(800.0 / 600.0, 45.0_f32.to_radians(), 0.1, 100.0);
@@ -184,38 +280,123 @@ impl Renderer {
 
         unsafe {
             gl::Enable(gl::DEPTH_TEST);
-            gl::Viewport(0, 0, 1900, 1100);
             gl::Disable(gl::CULL_FACE);
             gl::FrontFace(gl::CW);
         }
 
-        let mut angle = 0.0;
         // Loop until the user closes the window
         unsafe {
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
 
             // gl::ClearColor(0.1, 0.1, 0.1, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-            self.shader.use_program();
-            // spSimple.use_program();
+        }
 
-            let axis = glm::vec3(1.0, 1.0, 0.0); // Y axis
+        let hidden = camera.hidden_ant();
+        if self.instancing {
+            self.draw_instanced(world, hidden);
+        } else {
+            self.draw_per_object(world, hidden);
+        }
+        if self.passes.selection {
+            self.draw_selection(world);
+        }
+        if self.passes.pheromones {
+            // Translucent, so after everything solid.
+            self.pheromones.draw(world, &projection);
+        }
+    }
 
-            self.M = glm::rotate(&self.M, 0.01, &axis);
-            angle+=self.speed ;
-            self.V = glm::rotate(&self.V, angle, &glm::vec3(0.0,1.0,0.0));
-            // self.V = glm::rotate(&self.V, (PI)+0.01, &axis);
-            gl::UniformMatrix4fv(self.shader.get_uniform_location("P"),1,gl::FALSE,self.P.as_ptr());
-            gl::UniformMatrix4fv(self.shader.get_uniform_location("V"),1,gl::FALSE,self.V.as_ptr());
-            gl::UniformMatrix4fv(self.shader.get_uniform_location("M"),1,gl::FALSE,self.M.as_ptr());
-            // gl::UniformMatrix4fv(spConstant.get_uniform_location("M"),1,gl::FALSE,M.as_ptr());
-            gl::Uniform4f(self.shader.get_uniform_location("color") as GLint,1.0,1.0,1.0,1.0); 
+    /// Draws each model with one instanced call per primitive, leaving out
+    /// the ant with id `hidden`.
+    fn draw_instanced(&mut self, world: &World, hidden: Option<u32>) {
+        for batch in self.ant_batches.values_mut() {
+            batch.clear();
+        }
+        let ants = if self.passes.ants { world.ants.as_slice() } else { &[] };
+        for ant in ants.iter().filter(|ant| Some(ant.id) != hidden) {
+            let appearance = caste_appearance(ant.caste);
+            let s = appearance.scale;
+            let ant_matrix = glm::scale(&ant.transform(), &glm::vec3(s, s, s));
+            let color = self.ant_color(ant.id, &appearance);
+            self.ant_batches.entry(appearance.model).or_default().push(Instance::new(ant_matrix, color));
+        }
 
-            // gl::Uniform1i(self.shader.get_uniform_location("baseColorTexture"), 0);
+        self.shader_instanced.use_program();
+        self.ant_material.bind();
+        for (model, batch) in &self.ant_batches {
+            if batch.is_empty() {
+                continue;
+            }
+            self.instance_buffer.upload(batch);
+            let model = self.models.get(*model).unwrap();
+            if self.wireframe {
+                model.draw_instanced_wire(&self.shader_instanced, &self.instance_buffer);
+            } else {
+                model.draw_instanced(&self.shader_instanced, &self.instance_buffer);
+            }
+        }
 
+        let food_sources = if self.passes.food { world.food_sources.as_slice() } else { &[] };
+        self.instances.clear();
+        self.instances.extend(food_sources.iter().map(|food| Instance::new(food_transform(food), FOOD_COLOR)));
+        if !self.instances.is_empty() {
+            self.instance_buffer.upload(&self.instances);
+            self.lambert_instanced.use_program();
+            self.lambert_instanced.set_sampler("tex", 0);
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, self.dirt_texture);
+            }
+            let sphere = self.models.get("sphere").unwrap();
+            if self.wireframe {
+                sphere.draw_instanced_wire(&self.lambert_instanced, &self.instance_buffer);
+            } else {
+                sphere.draw_instanced(&self.lambert_instanced, &self.instance_buffer);
+            }
+        }
 
-        
+        let nests = if self.passes.nests { world.nests.as_slice() } else { &[] };
+        self.instances.clear();
+        self.instances.extend(nests.iter().map(|nest| Instance::new(nest_transform(nest), NEST_COLOR)));
+        if !self.instances.is_empty() {
+            self.instance_buffer.upload(&self.instances);
+            self.colored_instanced.use_program();
+            self.models.get("sphere").unwrap().draw_instanced_wire(&self.colored_instanced, &self.instance_buffer);
         }
+    }
+
+    fn ant_color(&self, id: u32, appearance: &CasteAppearance) -> glm::Vec4 {
+        if self.selection == Some(Selection::Ant(id)) { SELECTION_COLOR } else { appearance.color }
+    }
+
+    /// Draws a wireframe sphere around the selected entity.
+    fn draw_selection(&mut self, world: &World) {
+        let bounds = match self.selection {
+            Some(Selection::Ant(id)) => world.ants.iter().find(|ant| ant.id == id).map(|ant| (ant.position, ant_radius(ant.caste))),
+            Some(Selection::Food(id)) => world.food_sources.iter().find(|food| food.id == id).map(|food| (food.position, food_radius(food))),
+            Some(Selection::Nest(id)) => world.nests.iter().find(|nest| nest.id == id).map(|nest| (nest.position, nest.radius)),
+            None => None,
+        };
+        let Some((center, radius)) = bounds else {
+            return;
+        };
+        let sphere_radius = self.mesh("sphere").map_or(SPHERE_RADIUS, Mesh::bounding_radius);
+        let s = radius * SELECTION_MARGIN / sphere_radius;
+        let outline_matrix = glm::scale(&glm::translate(&glm::identity(), &center), &glm::vec3(s, s, s));
+        self.instance_buffer.upload(&[Instance::new(outline_matrix, SELECTION_COLOR)]);
+        self.colored_instanced.use_program();
+        self.models.get("sphere").unwrap().draw_instanced_wire(&self.colored_instanced, &self.instance_buffer);
+    }
+
+    /// Draws every object on its own, setting its uniforms first, leaving
+    /// out the ant with id `hidden`.
+    fn draw_per_object(&mut self, world: &World, hidden: Option<u32>) {
+        self.shader.use_program();
+        self.ant_material.bind();
+        // spSimple.use_program();
+
+        // self.V = glm::rotate(&self.V, (PI)+0.01, &axis);
         
 
         // myCube.draw_solid(true);
@@ -223,26 +404,49 @@ impl Renderer {
         // self.models.get_mut("sphere").unwrap().draw_wire(Some(true));
         
 
-        self.models.get_mut("ant").unwrap().draw_solid(false,&self.shader);
-        for pos in &self.random_pos_vector {
-            let mut randM: glm::Mat4 = glm::identity(); 
-            randM = glm::translate(&randM, &pos);
+        let ants = if self.passes.ants { world.ants.as_slice() } else { &[] };
+        for ant in ants.iter().filter(|ant| Some(ant.id) != hidden) {
+            let appearance = caste_appearance(ant.caste);
+            let s = appearance.scale;
+            let ant_matrix = glm::scale(&ant.transform(), &glm::vec3(s, s, s));
+            self.shader.set_mat4("M", &ant_matrix);
+            self.shader.set_vec4("color", &self.ant_color(ant.id, &appearance));
+            let model = self.models.get(appearance.model).unwrap();
+            if self.wireframe {
+                model.draw_wire(&self.shader);
+            } else {
+                model.draw_solid(&self.shader);
+            }
+        }
+        let food_sources = if self.passes.food { world.food_sources.as_slice() } else { &[] };
+        for food in food_sources {
+            let food_matrix = food_transform(food);
 
             self.lambert.use_program();
+            self.lambert.set_mat4("M", &food_matrix);
+            self.lambert.set_sampler("tex", 0);
             unsafe {
-                gl::UniformMatrix4fv(self.lambert.get_uniform_location("P"),1,gl::FALSE,self.P.as_ptr());
-                gl::UniformMatrix4fv(self.lambert.get_uniform_location("V"),1,gl::FALSE,self.V.as_ptr());
-                gl::UniformMatrix4fv(self.lambert.get_uniform_location("M"),1,gl::FALSE,randM.as_ptr());
-                // gl::UniformMatrix4fv(spConstant.get_uniform_location("M"),1,gl::FALSE,M.as_ptr());
                 gl::ActiveTexture(gl::TEXTURE0);
-                gl::BindTexture(gl::TEXTURE_2D, self.dirtTexture);
-                gl::Uniform1i(self.lambert.get_uniform_location("tex"),0);
-
-                gl::Uniform4f(self.lambert.get_uniform_location("color") as GLint,1.0,1.0,1.0,1.0); 
+                gl::BindTexture(gl::TEXTURE_2D, self.dirt_texture);
+            }
+            let sphere = self.models.get("sphere").unwrap();
+            if self.wireframe {
+                sphere.draw_wire(&self.lambert);
+            } else {
+                sphere.draw_solid(&self.lambert);
             }
-            self.models.get_mut("sphere").unwrap().draw_solid(false,&self.shader);
 
         }
+
+        let nests = if self.passes.nests { world.nests.as_slice() } else { &[] };
+        for nest in nests {
+            let nest_matrix = nest_transform(nest);
+
+            self.colored.use_program();
+            self.colored.set_mat4("M", &nest_matrix);
+            self.colored.set_vec4("color", &NEST_COLOR);
+            self.models.get("sphere").unwrap().draw_wire(&self.colored);
+        }
         // unsafe {
         //     let mut ms = glm::identity();
         //     ms = glm::translate(&ms, &glm::vec3(3.0,0.0,0.0));
@@ -275,9 +479,19 @@ impl Renderer {
 
 
 
-pub fn get_gl_string(variant: gl::types::GLenum) -> Option<&'static CStr> {
-    unsafe {
-        let s = gl::GetString(variant);
-        (!s.is_null()).then(|| CStr::from_ptr(s.cast()))
-    }
+/// Drawn radius of a food source, which shrinks as it is eaten.
+pub fn food_radius(food: &FoodSource) -> f32 {
+    let fill = if food.capacity > 0.0 { (food.stock / food.capacity).clamp(0.0, 1.0) } else { 0.0 };
+    food.radius * (0.3 + 0.7 * fill).sqrt()
+}
+
+/// The sphere model has a radius of SPHERE_RADIUS.
+fn food_transform(food: &FoodSource) -> glm::Mat4 {
+    let s = food_radius(food) / SPHERE_RADIUS;
+    glm::scale(&glm::translate(&glm::identity(), &food.position), &glm::vec3(s, s, s))
+}
+
+fn nest_transform(nest: &Nest) -> glm::Mat4 {
+    let s = nest.radius / SPHERE_RADIUS;
+    glm::scale(&glm::translate(&glm::identity(), &nest.position), &glm::vec3(s, s, s))
 }
@@ -0,0 +1,402 @@
+use std::path::Path;
+
+use egui::{Color32, Context, ViewportId};
+use nalgebra_glm as glm;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use cosmic_ant_sim::simulation::caste::{Caste, CasteProfile};
+use cosmic_ant_sim::simulation::pheromone::{Channel, ChannelParams};
+use cosmic_ant_sim::simulation::replay::{Input, Timeline};
+use cosmic_ant_sim::simulation::rng;
+use cosmic_ant_sim::simulation::stats::{Sample, Stats};
+
+use super::app_window::{MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE};
+use super::egui_painter::EguiPainter;
+use super::inspector::Inspection;
+use super::pheromone_view::ColorMap;
+use super::plot::{self, PlotView, Series};
+use super::renderer::{caste_appearance, Renderer};
+use crate::shader::shaderprogram::ShaderError;
+
+/// Lowest pheromone `min_value` the panel allows. At zero no chunk would ever
+/// be freed while diffusion keeps spreading them.
+const MIN_PHEROMONE_FLOOR: f32 = 1e-6;
+
+/// What the panels may change directly.
+pub struct Controls<'a> {
+    pub timeline: &'a mut Timeline,
+    pub paused: &'a mut bool,
+    pub playback_rate: &'a mut f32,
+    pub renderer: &'a mut Renderer,
+    pub stats: &'a Stats,
+    /// Directory of the running export, if any.
+    pub export: Option<&'a Path>,
+    /// Heading and readout of the ant the camera follows, if any.
+    pub follow: Option<(String, String)>,
+}
+
+/// What the panels ask of the app that `Controls` can't do.
+#[derive(Default)]
+pub struct Requests {
+    /// Restart from the scenario with this seed.
+    pub reset: Option<u64>,
+    /// Start or stop exporting.
+    pub toggle_export: bool,
+}
+
+/// The egui panels: simulation controls, caste and pheromone parameters, the
+/// inspector of the selection, statistics plots and render toggles, drawn
+/// over the scene.
+pub struct Ui {
+    ctx: Context,
+    state: egui_winit::State,
+    painter: EguiPainter,
+    pub visible: bool,
+    /// Seed the reset button restarts with.
+    reset_seed: u64,
+    /// Caste shown in the caste panel.
+    caste: Caste,
+    /// Tick range of the statistics plots.
+    plot_view: PlotView,
+}
+
+impl Ui {
+    /// Needs the window's GL context to be current.
+    pub fn new(window: &Window, seed: u64) -> Result<Self, ShaderError> {
+        let ctx = Context::default();
+        let state = egui_winit::State::new(
+            ctx.clone(),
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            window.theme(),
+            Some(EguiPainter::max_texture_side()),
+        );
+        let painter = EguiPainter::new()?;
+        Ok(Ui { ctx, state, painter, visible: true, reset_seed: seed, caste: Caste::Worker, plot_view: PlotView::default() })
+    }
+
+    pub fn reload_shaders(&mut self) {
+        self.painter.reload_shader();
+    }
+
+    /// Feeds `event` to egui; returns whether egui took it, in which case
+    /// the rest of the app must ignore it. Keys only count as taken while a
+    /// text field has focus, so Tab and friends keep working over panels.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let response = self.state.on_window_event(window, event);
+        match event {
+            WindowEvent::KeyboardInput { .. } => self.ctx.wants_keyboard_input(),
+            _ => response.consumed,
+        }
+    }
+
+    /// Lays out the panels, applies what they changed and paints them over
+    /// the frame.
+    pub fn frame(&mut self, window: &Window, controls: Controls) -> Requests {
+        let mut requests = Requests::default();
+        // Shader errors and the followed ant show even with the panels hidden.
+        let shader_errors: Vec<String> = controls.renderer.shader_errors().iter().map(|err| err.to_string()).collect();
+        if !self.visible && shader_errors.is_empty() && controls.follow.is_none() {
+            return requests;
+        }
+        let input = self.state.take_egui_input(window);
+        let output = self.ctx.clone().run(input, |ctx| {
+            if self.visible {
+                self.simulation_panel(ctx, controls.timeline, controls.paused, controls.playback_rate, &mut requests);
+                self.caste_panel(ctx, controls.timeline);
+                pheromone_panel(ctx, controls.timeline, controls.renderer);
+                inspector_panel(ctx, controls.timeline, controls.renderer);
+                self.statistics_panel(ctx, controls.stats, controls.export, &mut requests);
+                render_panel(ctx, controls.renderer);
+            }
+            if let Some((heading, readout)) = &controls.follow {
+                follow_overlay(ctx, heading, readout);
+            }
+            shader_error_panel(ctx, &shader_errors);
+        });
+        self.state.handle_platform_output(window, output.platform_output);
+
+        let primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        let size = window.inner_size();
+        self.painter.paint([size.width, size.height], output.pixels_per_point, &primitives, &output.textures_delta);
+        requests
+    }
+
+    fn simulation_panel(
+        &mut self,
+        ctx: &Context,
+        timeline: &mut Timeline,
+        paused: &mut bool,
+        playback_rate: &mut f32,
+        requests: &mut Requests,
+    ) {
+        egui::Window::new("Simulation").show(ctx, |ui| {
+            let world = timeline.world();
+            ui.label(format!("Tick {} ({:.1} s), {} ants", world.tick, world.time, world.ants.len()));
+            ui.label(format!("Food delivered {:.1} in {} trips", world.foraging.delivered, world.foraging.trips));
+            if timeline.is_replaying() {
+                ui.label(format!("Replaying, recorded up to tick {}", timeline.recording().end_tick()));
+            }
+            ui.horizontal(|ui| {
+                if ui.button(if *paused { "Play" } else { "Pause" }).clicked() {
+                    *paused = !*paused;
+                }
+                if ui.add_enabled(*paused, egui::Button::new("Step")).clicked() {
+                    timeline.step();
+                }
+            });
+            ui.add(egui::Slider::new(playback_rate, MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).logarithmic(true).text("speed"));
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut self.reset_seed));
+                if ui.button("Random").clicked() {
+                    self.reset_seed = rng::random_seed();
+                }
+            });
+            if ui.button("Reset").clicked() {
+                requests.reset = Some(self.reset_seed);
+            }
+        });
+    }
+
+    fn caste_panel(&mut self, ctx: &Context, timeline: &mut Timeline) {
+        egui::Window::new("Castes").default_open(false).show(ctx, |ui| {
+            egui::ComboBox::from_label("caste").selected_text(self.caste.name()).show_ui(ui, |ui| {
+                for caste in Caste::ALL {
+                    ui.selectable_value(&mut self.caste, caste, caste.name());
+                }
+            });
+            let mut profile = *timeline.world().castes().get(self.caste);
+            if edit_profile(ui, &mut profile) {
+                timeline.apply(Input::SetCasteProfile { caste: self.caste, profile });
+            }
+        });
+    }
+
+    fn statistics_panel(&mut self, ctx: &Context, stats: &Stats, export: Option<&Path>, requests: &mut Requests) {
+        egui::Window::new("Statistics").default_open(false).default_width(360.0).show(ctx, |ui| {
+            let samples = stats.samples();
+            let ticks: Vec<f64> = samples.iter().map(|sample| sample.tick as f64).collect();
+            let series = |name: &str, color: Color32, value: &dyn Fn(&Sample) -> f32| Series {
+                name: name.to_string(),
+                color,
+                values: samples.iter().map(value).collect(),
+            };
+            let view = &mut self.plot_view;
+
+            ui.label("Population");
+            let population: Vec<Series> = Caste::ALL
+                .into_iter()
+                .map(|caste| series(caste.name(), color(caste_appearance(caste).color.xyz()), &|sample| sample.population[caste.index()] as f32))
+                .collect();
+            plot::time_series(ui, view, &ticks, &population);
+
+            ui.label("Food collected");
+            plot::time_series(ui, view, &ticks, &[series("delivered", Color32::from_rgb(120, 200, 90), &|sample| sample.food_collected)]);
+
+            ui.label("Average energy");
+            plot::time_series(ui, view, &ticks, &[series("energy", Color32::from_rgb(230, 190, 80), &|sample| sample.average_energy)]);
+
+            ui.label("Pheromone mass");
+            let mass: Vec<Series> = Channel::ALL
+                .into_iter()
+                .map(|channel| series(channel.name(), color(ColorMap::for_channel(channel).at(1.0)), &|sample| sample.pheromone_mass[channel.index()]))
+                .collect();
+            plot::time_series(ui, view, &ticks, &mass);
+
+            ui.label("Births and deaths per tick");
+            plot::time_series(
+                ui,
+                view,
+                &ticks,
+                &[
+                    series("births", Color32::from_rgb(110, 190, 240), &Sample::births_per_tick),
+                    series("deaths", Color32::from_rgb(230, 100, 90), &Sample::deaths_per_tick),
+                ],
+            );
+
+            ui.label(format!(
+                "{} births and {} deaths recorded; scroll to zoom, drag to pan, double-click to reset",
+                stats.total_births(),
+                stats.total_deaths()
+            ));
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button(if export.is_some() { "Stop export" } else { "Export" }).clicked() {
+                    requests.toggle_export = true;
+                }
+                if let Some(dir) = export {
+                    ui.label(format!("writing to {}", dir.display()));
+                }
+            });
+        });
+    }
+}
+
+fn color(rgb: glm::Vec3) -> Color32 {
+    let [r, g, b] = [rgb.x, rgb.y, rgb.z].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color32::from_rgb(r, g, b)
+}
+
+/// A labelled drag value of at least `min` in a two-column grid; returns
+/// whether it changed.
+fn field(ui: &mut egui::Ui, label: &str, value: &mut f32, min: f32, speed: f64) -> bool {
+    ui.label(label);
+    let changed = ui.add(egui::DragValue::new(value).speed(speed).range(min..=f32::MAX)).changed();
+    ui.end_row();
+    changed
+}
+
+/// Edits every parameter of `profile`; returns whether any changed.
+fn edit_profile(ui: &mut egui::Ui, profile: &mut CasteProfile) -> bool {
+    let mut changed = false;
+    egui::Grid::new("caste profile").num_columns(2).show(ui, |ui| {
+        changed |= field(ui, "max speed", &mut profile.max_speed, 0.0, 0.01);
+        changed |= field(ui, "max acceleration", &mut profile.max_acceleration, 0.0, 0.01);
+        changed |= field(ui, "turn rate", &mut profile.turn_rate, 0.0, 0.01);
+        changed |= field(ui, "max bank", &mut profile.max_bank, 0.0, 0.01);
+        changed |= field(ui, "sensing radius", &mut profile.sensing_radius, 0.0, 0.01);
+        changed |= field(ui, "carrying capacity", &mut profile.carrying_capacity, 0.0, 0.01);
+        changed |= field(ui, "lifespan", &mut profile.lifespan, 0.0, 1.0);
+        changed |= field(ui, "energy burn rate", &mut profile.energy_burn_rate, 0.0, 0.0001);
+    });
+    ui.collapsing("Pheromone sensitivity", |ui| {
+        egui::Grid::new("caste sensitivity").num_columns(2).show(ui, |ui| {
+            let sensitivity = &mut profile.sensitivity;
+            changed |= field(ui, "trail", &mut sensitivity.trail, 0.0, 0.01);
+            changed |= field(ui, "alarm", &mut sensitivity.alarm, 0.0, 0.01);
+            changed |= field(ui, "recruitment", &mut sensitivity.recruitment, 0.0, 0.01);
+            changed |= field(ui, "home", &mut sensitivity.home, 0.0, 0.01);
+        });
+    });
+    ui.collapsing("Steering weights", |ui| {
+        egui::Grid::new("caste steering").num_columns(2).show(ui, |ui| {
+            let steering = &mut profile.steering;
+            changed |= field(ui, "seek", &mut steering.seek, 0.0, 0.01);
+            changed |= field(ui, "flee", &mut steering.flee, 0.0, 0.01);
+            changed |= field(ui, "wander", &mut steering.wander, 0.0, 0.01);
+            changed |= field(ui, "pheromone", &mut steering.pheromone, 0.0, 0.01);
+            changed |= field(ui, "avoid obstacles", &mut steering.avoid_obstacles, 0.0, 0.01);
+            changed |= field(ui, "separation", &mut steering.separation, 0.0, 0.01);
+            changed |= field(ui, "alignment", &mut steering.alignment, 0.0, 0.01);
+            changed |= field(ui, "cohesion", &mut steering.cohesion, 0.0, 0.01);
+        });
+    });
+    changed
+}
+
+fn pheromone_panel(ctx: &Context, timeline: &mut Timeline, renderer: &mut Renderer) {
+    egui::Window::new("Pheromones").default_open(false).show(ctx, |ui| {
+        for channel in Channel::ALL {
+            let mut params: ChannelParams = timeline.world().pheromones.params[channel.index()];
+            let mut changed = false;
+            ui.collapsing(channel.name(), |ui| {
+                egui::Grid::new(channel.name()).num_columns(2).show(ui, |ui| {
+                    changed |= field(ui, "diffusion", &mut params.diffusion, 0.0, 0.001);
+                    changed |= field(ui, "evaporation", &mut params.evaporation, 0.0, 0.001);
+                    changed |= field(ui, "min value", &mut params.min_value, MIN_PHEROMONE_FLOOR, 0.00001);
+                });
+            });
+            if changed {
+                timeline.apply(Input::SetChannelParams { channel, params });
+            }
+        }
+
+        ui.separator();
+        let settings = &mut renderer.pheromones.settings;
+        ui.horizontal(|ui| {
+            for channel in Channel::ALL {
+                ui.checkbox(&mut settings.channels[channel.index()], channel.name());
+            }
+        });
+        ui.checkbox(&mut settings.slices, "slice");
+        ui.add_enabled_ui(settings.slices, |ui| {
+            ui.horizontal(|ui| {
+                for (axis, name) in ["x", "y", "z"].into_iter().enumerate() {
+                    ui.radio_value(&mut settings.slice_axis, axis, name);
+                }
+            });
+            let radius = timeline.world().bounds_radius;
+            ui.add(egui::Slider::new(&mut settings.slice_position, -radius..=radius).text("position"));
+        });
+        ui.checkbox(&mut settings.points, "point cloud");
+        ui.checkbox(&mut settings.isosurface, "isosurface");
+        ui.add(egui::Slider::new(&mut settings.threshold, 0.001..=1.0).logarithmic(true).text("threshold"));
+    });
+}
+
+fn inspector_panel(ctx: &Context, timeline: &Timeline, renderer: &mut Renderer) {
+    egui::Window::new("Inspector").show(ctx, |ui| {
+        let Some(selection) = renderer.selection else {
+            ui.label("Click an ant, food source or nest to inspect it.");
+            return;
+        };
+        match Inspection::of(timeline.world(), selection) {
+            Some(inspection) => {
+                ui.heading(&inspection.heading);
+                egui::Grid::new("inspection").num_columns(2).striped(true).show(ui, |ui| {
+                    for (label, value) in &inspection.rows {
+                        ui.label(*label);
+                        ui.monospace(value);
+                        ui.end_row();
+                    }
+                });
+            },
+            None => {
+                ui.label("The selection no longer exists.");
+            },
+        }
+        if ui.button("Clear").clicked() {
+            renderer.selection = None;
+        }
+    });
+}
+
+/// What the followed ant is doing, in the bottom left corner of the view.
+fn follow_overlay(ctx: &Context, heading: &str, readout: &str) {
+    egui::Area::new(egui::Id::new("follow overlay"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.strong(heading);
+                // State and load, then the pheromones sensed.
+                for part in readout.split(" | ") {
+                    ui.label(part);
+                }
+            });
+        });
+}
+
+fn shader_error_panel(ctx: &Context, errors: &[String]) {
+    if errors.is_empty() {
+        return;
+    }
+    egui::Window::new("Shader errors").anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0]).show(ctx, |ui| {
+        ui.label("Drawing with the last working version until the files are fixed and saved.");
+        for err in errors {
+            ui.separator();
+            ui.colored_label(ui.visuals().error_fg_color, egui::RichText::new(err).monospace());
+        }
+    });
+}
+
+fn render_panel(ctx: &Context, renderer: &mut Renderer) {
+    egui::Window::new("Rendering").default_open(false).show(ctx, |ui| {
+        ui.checkbox(&mut renderer.instancing, "instanced drawing");
+        ui.checkbox(&mut renderer.wireframe, "wireframe");
+        ui.separator();
+        let passes = &mut renderer.passes;
+        ui.checkbox(&mut passes.ants, "ants");
+        ui.checkbox(&mut passes.food, "food");
+        ui.checkbox(&mut passes.nests, "nests");
+        ui.checkbox(&mut passes.selection, "selection outline");
+        ui.checkbox(&mut passes.pheromones, "pheromones");
+    });
+}
@@ -0,0 +1,143 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::simulation::export::{ExportConfig, Exporter};
+use crate::simulation::replay::Timeline;
+use crate::simulation::snapshot;
+use crate::simulation::world::World;
+
+/// What a headless run does besides stepping the world.
+#[derive(Clone, Debug)]
+pub struct HeadlessConfig {
+    pub ticks: u64,
+    /// Directory the export, `summary.toml` and `snapshots/` are written to.
+    pub output_dir: PathBuf,
+    /// Sampling interval, format and columns of `stats` and `trajectories`.
+    pub export: ExportConfig,
+    /// Ticks between snapshots, 0 for none. Each writes a full binary snapshot
+    /// that runs can be resumed from, and a CSV of the ants.
+    pub snapshot_every: u64,
+}
+
+/// Outcome of a headless run, also written to `summary.toml`.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub seed: u64,
+    pub ticks: u64,
+    pub sim_time: f64,
+    pub wall_time: Duration,
+    pub ants_alive: usize,
+    pub births: u64,
+    pub deaths: u64,
+    pub delivered: f32,
+    pub trips: u32,
+    pub mean_income: f32,
+    pub state_hash: u64,
+}
+
+/// Steps `timeline` `config.ticks` times as fast as possible, writing
+/// statistics and snapshots to `config.output_dir` on the way. Inputs recorded
+/// in the timeline are replayed as their ticks come up.
+pub fn run(timeline: &mut Timeline, config: &HeadlessConfig) -> Result<Summary, String> {
+    let snapshot_dir = config.output_dir.join("snapshots");
+    let dir = if config.snapshot_every > 0 { &snapshot_dir } else { &config.output_dir };
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut exporter = Exporter::create(&config.output_dir, &config.export)?;
+    exporter.sample(timeline.world())?;
+
+    let start = Instant::now();
+    let progress_every = (config.ticks / 10).max(1);
+    for done in 1..=config.ticks {
+        timeline.step();
+        let world = timeline.world();
+
+        exporter.record(world)?;
+        if config.snapshot_every > 0 && done % config.snapshot_every == 0 {
+            snapshot::save_to_file(world, snapshot_dir.join(format!("tick_{:08}.antsnap", world.tick)))?;
+            write_ants(&snapshot_dir.join(format!("ants_{:08}.csv", world.tick)), world)?;
+        }
+        if done % progress_every == 0 {
+            let rate = done as f64 / start.elapsed().as_secs_f64().max(1e-9);
+            eprintln!("tick {}/{} ({:.0} ticks/s, {} ants)", done, config.ticks, rate, world.ants.len());
+        }
+    }
+
+    let world = timeline.world();
+    let exported = exporter.finish(world)?;
+    let summary = Summary {
+        seed: world.seed(),
+        ticks: world.tick,
+        sim_time: world.time,
+        wall_time: start.elapsed(),
+        ants_alive: world.ants.len(),
+        births: exported.total_births(),
+        deaths: exported.total_deaths(),
+        delivered: world.foraging.delivered,
+        trips: world.foraging.trips,
+        mean_income: world.foraging.mean_income(world.time),
+        state_hash: world.state_hash(),
+    };
+    let summary_path = config.output_dir.join("summary.toml");
+    fs::write(&summary_path, summary.to_toml()).map_err(|e| write_error(&summary_path, e))?;
+    Ok(summary)
+}
+
+impl Summary {
+    pub fn to_toml(&self) -> String {
+        format!(
+            "seed = {}\nticks = {}\nsim_time = {}\nwall_time = {}\nants_alive = {}\n\
+             births = {}\ndeaths = {}\ndelivered = {}\ntrips = {}\nmean_income = {}\nstate_hash = \"{:016x}\"\n",
+            self.seed,
+            self.ticks,
+            self.sim_time,
+            self.wall_time.as_secs_f64(),
+            self.ants_alive,
+            self.births,
+            self.deaths,
+            self.delivered,
+            self.trips,
+            self.mean_income,
+            self.state_hash,
+        )
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>, String> {
+    File::create(path).map(BufWriter::new).map_err(|e| format!("Failed to create {}: {}", path.display(), e))
+}
+
+fn write_error(path: &Path, err: std::io::Error) -> String {
+    format!("Failed to write {}: {}", path.display(), err)
+}
+
+/// One row per living ant.
+fn write_ants(path: &Path, world: &World) -> Result<(), String> {
+    let mut out = create(path)?;
+    let mut write = || -> std::io::Result<()> {
+        writeln!(out, "id,caste,state,x,y,z,vx,vy,vz,energy,age,carrying")?;
+        for ant in &world.ants {
+            let (p, v) = (ant.position, ant.velocity);
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                ant.id,
+                ant.caste.name(),
+                ant.state.name(),
+                p.x,
+                p.y,
+                p.z,
+                v.x,
+                v.y,
+                v.z,
+                ant.energy,
+                ant.age,
+                ant.carrying
+            )?;
+        }
+        out.flush()
+    };
+    write().map_err(|e| write_error(path, e))
+}
@@ -0,0 +1,4 @@
+// GL-free parts of the simulator, shared by the windowed app, the headless
+// runner and the benchmarks.
+pub mod headless;
+pub mod simulation;
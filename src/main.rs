@@ -4,23 +4,96 @@ extern crate nalgebra_glm;
 
 
 use app::app_window::{window_attributes, App};
+use app::render_bench::RenderBench;
 
 mod shader;
 mod models;
 mod utils;
 mod app;
+mod options;
 
-
-use utils::constants::*;
+use cosmic_ant_sim::simulation;
+use options::{Options, USAGE};
+use simulation::caste::CasteTable;
+use simulation::replay::{Recording, Timeline};
+use simulation::scenario::Scenario;
+use simulation::{rng, snapshot, world};
+use cosmic_ant_sim::headless::{self, HeadlessConfig};
 
 
 use winit::event_loop::{ControlFlow, EventLoop};
-use glutin::config::{Config, ConfigTemplateBuilder, GetGlConfig};
-use winit::window::{Window, WindowAttributes, WindowId};
-use glutin_winit::{DisplayBuilder, GlWindow};
+use glutin::config::ConfigTemplateBuilder;
+use glutin_winit::DisplayBuilder;
 
 
 fn main() {
+    let options = Options::from_env().unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        std::process::exit(2);
+    });
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    let scenario = match &options.scenario {
+        Some(path) => Scenario::load(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }),
+        None => Scenario::default(),
+    };
+    let castes = CasteTable::load_or_default();
+    let seed = options.seed.or(scenario.seed).unwrap_or_else(rng::random_seed);
+    let build = || match &options.snapshot {
+        Some(path) => snapshot::load_from_file(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }),
+        None => scenario.build(castes.clone(), seed),
+    };
+    let mut timeline = match &options.replay {
+        Some(path) => Recording::load_from_file(path).and_then(Timeline::from_recording).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }),
+        None => Timeline::new(build()),
+    };
+    println!("Seed: {}", timeline.world().seed());
+
+    if let Some(ticks) = options.verify_determinism {
+        match world::verify_determinism(build, ticks) {
+            Ok(hash) => println!("Deterministic over {} ticks, state hash {:016x}", ticks, hash),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
+    if options.headless {
+        let default_ticks = match options.replay {
+            Some(_) => timeline.recording().end_tick() - timeline.tick(),
+            None => scenario.ticks,
+        };
+        let config = HeadlessConfig {
+            ticks: options.ticks.unwrap_or(default_ticks),
+            output_dir: options.output_dir,
+            export: options.export,
+            snapshot_every: options.snapshot_every,
+        };
+        // Nothing seeks in a headless run.
+        timeline.set_keyframe_interval(0);
+        match headless::run(&mut timeline, &config) {
+            Ok(summary) => print!("{}", summary.to_toml()),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
     let event_loop = EventLoop::new().unwrap();
 
     // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
@@ -35,12 +108,21 @@ fn main() {
     // let mut app = App::new(template, display_builder);;
     // event_loop.run_app(&mut app);
     let template =
-    ConfigTemplateBuilder::new().with_alpha_size(8).with_transparency(cfg!(cgl_backend));
+    ConfigTemplateBuilder::new().with_alpha_size(8).with_transparency(cfg!(target_os = "macos"));
 
     let display_builder = DisplayBuilder::new().with_window_attributes(Some(window_attributes()));
 
-    let mut app = App::new(template, display_builder);
-    event_loop.run_app(&mut app);
+    let reset = move |seed| scenario.build(castes.clone(), seed);
+    let mut app = App::new(template, display_builder, timeline, Box::new(reset));
+    app.set_export(options.output_dir, options.export);
+    app.set_capture(options.capture);
+    if options.render_bench {
+        app.run_render_bench(RenderBench::new(seed));
+    }
+    if let Err(err) = event_loop.run_app(&mut app) {
+        eprintln!("Event loop failed: {}", err);
+        std::process::exit(1);
+    }
 
     // app.exit_state
 }
@@ -0,0 +1,105 @@
+use std::mem::{offset_of, size_of, size_of_val};
+use std::ptr;
+
+use gl::types::*;
+use nalgebra_glm as glm;
+
+/// First attribute location of the per-instance data in the `*_instanced`
+/// shaders: the model matrix takes this and the next three, the colour the one after.
+pub const INSTANCE_ATTRIB_LOCATION: GLuint = 3;
+
+/// Per-instance data of an instanced draw.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Instance {
+    pub model: glm::Mat4,
+    pub color: glm::Vec4,
+}
+
+impl Instance {
+    pub fn new(model: glm::Mat4, color: glm::Vec4) -> Self {
+        Instance { model, color }
+    }
+}
+
+/// A vertex buffer of `Instance`s, refilled every frame.
+pub struct InstanceBuffer {
+    vbo: GLuint,
+    /// Instances the buffer has storage for.
+    capacity: usize,
+    len: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new() -> Self {
+        let mut vbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+        }
+        InstanceBuffer { vbo, capacity: 0, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Replaces the contents with `instances`. The old storage is orphaned
+    /// rather than overwritten, so draws still reading it don't stall the upload.
+    pub fn upload(&mut self, instances: &[Instance]) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+        }
+        self.len = instances.len();
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.capacity * size_of::<Instance>()) as GLsizeiptr,
+                ptr::null(),
+                gl::STREAM_DRAW,
+            );
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                size_of_val(instances) as GLsizeiptr,
+                instances.as_ptr() as *const _,
+            );
+        }
+    }
+
+    /// Points the instance attributes of the currently bound vertex array at
+    /// this buffer, advancing once per instance.
+    pub fn attach(&self) {
+        let stride = size_of::<Instance>() as GLsizei;
+        let column = size_of::<glm::Vec4>();
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            for i in 0..4 {
+                let location = INSTANCE_ATTRIB_LOCATION + i;
+                let offset = offset_of!(Instance, model) + i as usize * column;
+                gl::VertexAttribPointer(location, 4, gl::FLOAT, gl::FALSE, stride, offset as *const _);
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribDivisor(location, 1);
+            }
+            let location = INSTANCE_ATTRIB_LOCATION + 4;
+            let offset = offset_of!(Instance, color);
+            gl::VertexAttribPointer(location, 4, gl::FLOAT, gl::FALSE, stride, offset as *const _);
+            gl::EnableVertexAttribArray(location);
+            gl::VertexAttribDivisor(location, 1);
+        }
+    }
+}
+
+impl Default for InstanceBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InstanceBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
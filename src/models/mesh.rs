@@ -0,0 +1,246 @@
+use std::mem::size_of;
+use std::ptr;
+
+use gl::types::*;
+use nalgebra_glm as glm;
+
+use super::instances::{InstanceBuffer, INSTANCE_ATTRIB_LOCATION};
+
+/// Per-vertex attributes a mesh can have, each bound to a fixed shader
+/// location. Locations 3 to 7 belong to the instance attributes, so the
+/// optional ones start after them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    /// A `vec4` in the shaders, with `w` 1.
+    Position,
+    /// A `vec4` in the shaders, with `w` 0.
+    Normal,
+    TexCoord,
+    /// Tangent with the bitangent's sign in `w`, as glTF stores it.
+    Tangent,
+    Color,
+}
+
+impl Attribute {
+    pub const fn location(self) -> GLuint {
+        match self {
+            Attribute::Position => 0,
+            Attribute::Normal => 1,
+            Attribute::TexCoord => 2,
+            Attribute::Tangent => 8,
+            Attribute::Color => 9,
+        }
+    }
+
+    /// Floats per vertex in the vertex buffer.
+    pub const fn components(self) -> usize {
+        match self {
+            Attribute::TexCoord => 2,
+            _ => 4,
+        }
+    }
+}
+
+const _: () = assert!(Attribute::Tangent.location() > INSTANCE_ATTRIB_LOCATION + 4);
+
+/// Where one attribute sits in an interleaved vertex.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexAttribute {
+    pub attribute: Attribute,
+    /// Floats from the start of the vertex.
+    pub offset: usize,
+}
+
+/// How the vertices of a mesh are interleaved in its vertex buffer: the
+/// attributes it has, in `Attribute` order.
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+    /// Floats per vertex.
+    pub stride: usize,
+}
+
+impl VertexLayout {
+    fn new(attributes: impl IntoIterator<Item = Attribute>) -> Self {
+        let mut stride = 0;
+        let attributes = attributes
+            .into_iter()
+            .map(|attribute| {
+                let offset = stride;
+                stride += attribute.components();
+                VertexAttribute { attribute, offset }
+            })
+            .collect();
+        VertexLayout { attributes, stride }
+    }
+}
+
+/// Triangle geometry owned on the CPU, for drawing once uploaded as a
+/// `GpuMesh` and for reading from safe code, e.g. bounds with
+/// `bounding_radius`.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<glm::Vec3>,
+    pub normals: Vec<glm::Vec3>,
+    pub tex_coords: Vec<glm::Vec2>,
+    pub tangents: Option<Vec<glm::Vec4>>,
+    pub colors: Option<Vec<glm::Vec4>>,
+    /// Vertex indices, three per triangle, or `None` if every three vertices
+    /// are a triangle.
+    pub indices: Option<Vec<u32>>,
+}
+
+impl Mesh {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Distance of the vertex furthest from the origin.
+    pub fn bounding_radius(&self) -> f32 {
+        self.positions.iter().map(glm::length).fold(0.0, f32::max)
+    }
+
+    pub fn layout(&self) -> VertexLayout {
+        let optional = [(Attribute::Tangent, self.tangents.is_some()), (Attribute::Color, self.colors.is_some())];
+        VertexLayout::new(
+            [Attribute::Position, Attribute::Normal, Attribute::TexCoord]
+                .into_iter()
+                .chain(optional.into_iter().filter(|&(_, present)| present).map(|(attribute, _)| attribute)),
+        )
+    }
+
+    /// Checks that every attribute has a value per vertex and that the
+    /// indices make whole triangles of existing vertices.
+    pub fn validate(&self) -> Result<(), String> {
+        let count = self.vertex_count();
+        let lengths = [
+            ("normals", Some(self.normals.len())),
+            ("texture coordinates", Some(self.tex_coords.len())),
+            ("tangents", self.tangents.as_ref().map(Vec::len)),
+            ("colors", self.colors.as_ref().map(Vec::len)),
+        ];
+        for (name, length) in lengths {
+            if let Some(length) = length.filter(|&length| length != count) {
+                return Err(format!("Mesh has {} vertices but {} {}", count, length, name));
+            }
+        }
+        let corners = match &self.indices {
+            Some(indices) => {
+                if let Some(index) = indices.iter().find(|&&index| index as usize >= count) {
+                    return Err(format!("Mesh index {} is past its {} vertices", index, count));
+                }
+                indices.len()
+            },
+            None => count,
+        };
+        if corners % 3 != 0 {
+            return Err(format!("Mesh has {} triangle corners, not a multiple of 3", corners));
+        }
+        Ok(())
+    }
+
+    /// The vertices interleaved as `layout` describes.
+    fn interleave(&self, layout: &VertexLayout) -> Vec<f32> {
+        let mut data = Vec::with_capacity(self.vertex_count() * layout.stride);
+        for i in 0..self.vertex_count() {
+            for VertexAttribute { attribute, .. } in &layout.attributes {
+                match attribute {
+                    Attribute::Position => data.extend_from_slice(&[self.positions[i].x, self.positions[i].y, self.positions[i].z, 1.0]),
+                    Attribute::Normal => data.extend_from_slice(&[self.normals[i].x, self.normals[i].y, self.normals[i].z, 0.0]),
+                    Attribute::TexCoord => data.extend_from_slice(self.tex_coords[i].as_slice()),
+                    Attribute::Tangent => data.extend_from_slice(self.tangents.as_ref().unwrap()[i].as_slice()),
+                    Attribute::Color => data.extend_from_slice(self.colors.as_ref().unwrap()[i].as_slice()),
+                }
+            }
+        }
+        data
+    }
+}
+
+/// A `Mesh` uploaded to a vertex array, freed when dropped.
+pub struct GpuMesh {
+    vao: GLuint,
+    vbo: GLuint,
+    /// Zero for meshes without indices.
+    ebo: GLuint,
+    /// Indices, or vertices if there are none, to draw.
+    count: GLsizei,
+}
+
+impl GpuMesh {
+    pub fn new(mesh: &Mesh) -> Result<Self, String> {
+        mesh.validate()?;
+        let layout = mesh.layout();
+        let vertices = mesh.interleave(&layout);
+        let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * size_of::<f32>()) as GLsizeiptr,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (layout.stride * size_of::<f32>()) as GLsizei;
+            for VertexAttribute { attribute, offset } in &layout.attributes {
+                let location = attribute.location();
+                let offset = offset * size_of::<f32>();
+                gl::VertexAttribPointer(location, attribute.components() as GLint, gl::FLOAT, gl::FALSE, stride, offset as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+            if let Some(indices) = &mesh.indices {
+                gl::GenBuffers(1, &mut ebo);
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+                gl::BufferData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    (indices.len() * size_of::<u32>()) as GLsizeiptr,
+                    indices.as_ptr() as *const _,
+                    gl::STATIC_DRAW,
+                );
+            }
+            gl::BindVertexArray(0);
+        }
+        let count = mesh.indices.as_ref().map_or(mesh.vertex_count(), Vec::len) as GLsizei;
+        Ok(GpuMesh { vao, vbo, ebo, count })
+    }
+
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            if self.ebo != 0 {
+                gl::DrawElements(gl::TRIANGLES, self.count, gl::UNSIGNED_INT, ptr::null());
+            } else {
+                gl::DrawArrays(gl::TRIANGLES, 0, self.count);
+            }
+        }
+    }
+
+    /// Draws one copy per entry of `instances` in a single call.
+    pub fn draw_instanced(&self, instances: &InstanceBuffer) {
+        let copies = instances.len() as GLsizei;
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            instances.attach();
+            if self.ebo != 0 {
+                gl::DrawElementsInstanced(gl::TRIANGLES, self.count, gl::UNSIGNED_INT, ptr::null(), copies);
+            } else {
+                gl::DrawArraysInstanced(gl::TRIANGLES, 0, self.count, copies);
+            }
+        }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+            if self.ebo != 0 {
+                gl::DeleteBuffers(1, &self.ebo);
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod model;
+pub mod mesh;
+pub mod instances;
 pub mod sphere;
 pub mod shuttlebug;
 // pub mod torus;
@@ -1,30 +1,46 @@
-use gl::types::*;
-
 use crate::shader::shaderprogram::ShaderProgram;
+use super::instances::InstanceBuffer;
+use super::mesh::{GpuMesh, Mesh};
 
-#[derive(Clone)]
-pub struct ModelParams {
-    pub vertex_count: i32,
-    pub vertices: *const gl::types::GLvoid,
-    pub normals: *const gl::types::GLvoid,
-    pub vertex_normals: *const gl::types::GLvoid,
-    pub tex_coords: *const gl::types::GLvoid,
-}
+pub trait Model {
+    /// Geometry of what `draw_solid` draws, in model space.
+    fn mesh(&self) -> &Mesh;
 
-pub trait Model{
-    fn read_model_params(&self) -> &ModelParams;
+    fn gpu_mesh(&self) -> &GpuMesh;
 
-    fn get_model_params(&mut self) -> &mut ModelParams;
+    /// Binds the model's textures and points `shader`'s samplers at them.
+    fn bind_textures(&self, _shader: &ShaderProgram) {}
 
-    fn draw_solid(&mut self, smooth: bool,shader: &ShaderProgram);
+    fn draw_solid(&self, shader: &ShaderProgram) {
+        self.bind_textures(shader);
+        self.gpu_mesh().draw();
+    }
 
-    fn draw_wire(&mut self, smooth: Option<bool>, shader: &ShaderProgram) {
-        let smooth = smooth.unwrap_or(false);
+    /// Draws one copy per entry of `instances` with a single draw call.
+    /// `shader` must read the instance attributes.
+    fn draw_instanced(&self, shader: &ShaderProgram, instances: &InstanceBuffer) {
+        self.bind_textures(shader);
+        self.gpu_mesh().draw_instanced(instances);
+    }
+
+    fn draw_wire(&self, shader: &ShaderProgram) {
         unsafe {
             gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-            self.draw_solid(smooth, shader);
+        }
+        self.draw_solid(shader);
+        unsafe {
             gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
         }
     }
 
+    /// `draw_instanced` in wireframe.
+    fn draw_instanced_wire(&self, shader: &ShaderProgram, instances: &InstanceBuffer) {
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+        }
+        self.draw_instanced(shader, instances);
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use std::env;
+use std::path::PathBuf;
+
+use cosmic_ant_sim::simulation::export::{ExportConfig, ExportFormat};
+
+use crate::app::capture::CaptureConfig;
+
+/// Ticks stepped by `--verify-determinism` when no count is given.
+const DEFAULT_VERIFY_TICKS: u64 = 600;
+const DEFAULT_OUTPUT_DIR: &str = "output";
+
+pub const USAGE: &str = "\
+Usage: cosmic-ant-sim [options]
+
+Options:
+  --scenario <path>              Scenario to start from (default: the built-in
+                                 assets/scenarios/default.toml)
+  --snapshot <path>              Resume from a snapshot instead of a scenario
+  --replay <path>                Play back a recording saved with F6; headless runs
+                                 replay it to its end unless --ticks is given
+  --seed <u64>                   Seed of every random decision, overrides the
+                                 scenario's (default: random)
+  --headless                     Run without a window, see the options below
+  --ticks <n>                    Ticks to run headless (default: from the scenario)
+  --output <dir>                 Where headless runs write stats.csv, summary.toml
+                                 and snapshots/, and F7 exports go (default: output)
+  --stats-every <ticks>          Ticks between exported samples (default: 60)
+  --export-format <csv|binary>   Format of stats and trajectories (default: csv)
+  --columns <a,b,...>            Export only these columns (default: all)
+  --trajectories                 Also export every ant at every sample
+  --capture-dir <dir>            Where F12 screenshots and F8 recordings go
+                                 (default: captures)
+  --record-ticks <n>             Ticks between recorded frames (default: 2)
+  --gif                          Also encode F8 recordings into a GIF
+  --snapshot-every <ticks>       Ticks between snapshots, 0 for none (default: 0)
+  --verify-determinism [ticks]   Run the scenario twice with the same seed and
+                                 check both runs stay bit-identical
+  --render-bench                 Time drawing 100, 10k and 100k ants one by one
+                                 and instanced, print the frame times and exit
+  -h, --help                     Print this help";
+
+/// Command-line options.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub scenario: Option<PathBuf>,
+    /// Snapshot to resume from; the scenario then only supplies the run length.
+    pub snapshot: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub headless: bool,
+    /// Overrides the scenario's run length.
+    pub ticks: Option<u64>,
+    pub output_dir: PathBuf,
+    pub export: ExportConfig,
+    pub capture: CaptureConfig,
+    pub snapshot_every: u64,
+    /// Number of ticks to compare, if a determinism check was requested.
+    pub verify_determinism: Option<u64>,
+    pub render_bench: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            scenario: None,
+            snapshot: None,
+            replay: None,
+            seed: None,
+            headless: false,
+            ticks: None,
+            output_dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+            export: ExportConfig::default(),
+            capture: CaptureConfig::default(),
+            snapshot_every: 0,
+            verify_determinism: None,
+            render_bench: false,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--scenario" => options.scenario = Some(PathBuf::from(value()?)),
+                "--snapshot" => options.snapshot = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--seed" => options.seed = Some(parse_number(&arg, &value()?)?),
+                "--headless" => options.headless = true,
+                "--ticks" => options.ticks = Some(parse_number(&arg, &value()?)?),
+                "--output" => options.output_dir = PathBuf::from(value()?),
+                "--stats-every" => options.export.every = parse_number(&arg, &value()?)?,
+                "--export-format" => options.export.format = ExportFormat::parse(&value()?)?,
+                "--columns" => options.export.columns = value()?.split(',').map(|name| name.trim().to_string()).collect(),
+                "--trajectories" => options.export.trajectories = true,
+                "--capture-dir" => options.capture.dir = PathBuf::from(value()?),
+                "--record-ticks" => options.capture.ticks_per_frame = parse_count(&arg, &value()?)?,
+                "--gif" => options.capture.gif = true,
+                "--snapshot-every" => options.snapshot_every = parse_number(&arg, &value()?)?,
+                "--verify-determinism" => {
+                    let ticks = match args.next_if(|next| !next.starts_with('-')) {
+                        Some(value) => parse_number(&arg, &value)?,
+                        None => DEFAULT_VERIFY_TICKS,
+                    };
+                    options.verify_determinism = Some(ticks);
+                },
+                "--render-bench" => options.render_bench = true,
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number(option: &str, value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("{} expects a non-negative integer, got {:?}", option, value))
+}
+
+/// Parses a whole number from 1 to `u32::MAX`.
+fn parse_count(option: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&count| count >= 1)
+        .ok_or_else(|| format!("{} expects an integer from 1 to {}, got {:?}", option, u32::MAX, value))
+}
@@ -1 +1,3 @@
 pub mod shaderprogram;
+pub mod preprocess;
+pub mod uniform_block;
@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Why a shader source could not be preprocessed.
+#[derive(Clone, Debug)]
+pub struct PreprocessError {
+    /// File with the failing `#include`, or the top file if it can't be read.
+    pub file: String,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+/// Expands the `#include "file"` directives of the GLSL file at `path`,
+/// resolving names against the including file's directory, and inserts
+/// `#define <define>` lines (e.g. `INSTANCED` or `LIGHT_COUNT 2`) after its
+/// `#version`.
+///
+/// Each file becomes a GLSL source string number, set with `#line` so that
+/// driver logs (`<number>:<line>`) point at the file and line the code came
+/// from. `files` is filled with them by number, the top file first, even if
+/// preprocessing fails, so they can all be watched for changes. Includes are
+/// expanded whatever `#if` they are in; use include guards where needed.
+pub fn preprocess(path: &str, defines: &[String], files: &mut Vec<String>) -> Result<String, PreprocessError> {
+    files.clear();
+    let mut text = String::new();
+    expand(path, None, defines, files, &mut Vec::new(), &mut text)?;
+    Ok(text)
+}
+
+/// Source string number of `path`, given one on first use.
+fn source_number(files: &mut Vec<String>, path: &str) -> usize {
+    files.iter().position(|file| file == path).unwrap_or_else(|| {
+        files.push(path.to_string());
+        files.len() - 1
+    })
+}
+
+/// Appends `path` to `text`. `included_from` is the file and line of the
+/// `#include` for anything but the top file, and `stack` the files being
+/// expanded, to catch cycles.
+fn expand(
+    path: &str,
+    included_from: Option<(&str, u32)>,
+    defines: &[String],
+    files: &mut Vec<String>,
+    stack: &mut Vec<(PathBuf, String)>,
+    text: &mut String,
+) -> Result<(), PreprocessError> {
+    let number = source_number(files, path);
+    let error = |message: String| match included_from {
+        Some((file, line)) => PreprocessError { file: file.to_string(), line: Some(line), message },
+        None => PreprocessError { file: path.to_string(), line: None, message },
+    };
+
+    let key = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    if let Some(start) = stack.iter().position(|(open, _)| *open == key) {
+        let cycle: Vec<&str> = stack[start..].iter().map(|(_, name)| name.as_str()).chain([path]).collect();
+        return Err(error(format!("Include cycle: {}", cycle.join(" -> "))));
+    }
+    let bytes = fs::read(path).map_err(|e| error(format!("Failed to read {}: {}", path, e)))?;
+    let source = String::from_utf8_lossy(&bytes);
+    stack.push((key, path.to_string()));
+
+    if included_from.is_some() {
+        text.push_str(&format!("#line 1 {}\n", number));
+    } else if !source.lines().any(|line| line.trim_start().starts_with("#version")) {
+        push_defines(text, defines, 1, number);
+    }
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        let directive = line.trim_start();
+        if let Some(rest) = directive.strip_prefix("#include") {
+            let name = rest.trim().strip_prefix('"').and_then(|rest| rest.strip_suffix('"')).ok_or_else(|| {
+                PreprocessError {
+                    file: path.to_string(),
+                    line: Some(line_number),
+                    message: "Expected #include \"file\"".to_string(),
+                }
+            })?;
+            let included = resolve(path, name);
+            expand(&included, Some((path, line_number)), defines, files, stack, text)?;
+            text.push_str(&format!("#line {} {}\n", line_number + 1, number));
+        } else {
+            text.push_str(line);
+            text.push('\n');
+            if included_from.is_none() && directive.starts_with("#version") {
+                push_defines(text, defines, line_number + 1, number);
+            }
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+/// Path of `name` included from the file at `from`, with `..` folded into
+/// the directories before it so each file has one name in messages.
+fn resolve(from: &str, name: &str) -> String {
+    let mut path = PathBuf::new();
+    for component in Path::new(from).parent().unwrap_or(Path::new("")).join(name).components() {
+        match component {
+            Component::ParentDir if matches!(path.components().next_back(), Some(Component::Normal(_))) => {
+                path.pop();
+            },
+            Component::CurDir => {},
+            component => path.push(component),
+        }
+    }
+    path.to_string_lossy().to_string()
+}
+
+/// Appends `defines`, then numbers the next line `line` of source `number`.
+fn push_defines(text: &mut String, defines: &[String], line: u32, number: usize) {
+    if defines.is_empty() {
+        return;
+    }
+    for define in defines {
+        text.push_str(&format!("#define {}\n", define));
+    }
+    text.push_str(&format!("#line {} {}\n", line, number));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `files` into a fresh directory named after the test and returns its path.
+    fn write_sources(test: &str, files: &[(&str, &str)]) -> String {
+        let dir = std::env::temp_dir().join(format!("preprocess-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        for (name, source) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn include_cycle_names_every_file() {
+        let dir = write_sources("cycle", &[("a.glsl", "#include \"b.glsl\"\n"), ("b.glsl", "\n#include \"a.glsl\"\n")]);
+        let (a, b) = (format!("{}/a.glsl", dir), format!("{}/b.glsl", dir));
+        let mut files = Vec::new();
+        let error = preprocess(&a, &[], &mut files).unwrap_err();
+        assert_eq!(error.file, b);
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.message, format!("Include cycle: {} -> {} -> {}", a, b, a));
+        assert_eq!(files, [a, b]);
+    }
+
+    #[test]
+    fn includes_are_numbered_with_line_directives() {
+        let dir = write_sources(
+            "lines",
+            &[
+                ("main.glsl", "#version 330 core\n#include \"lib/common.glsl\"\nvoid main() {}\n"),
+                ("lib/common.glsl", "float x;\n#include \"../util.glsl\"\nfloat y;\n"),
+                ("util.glsl", "float z;\n"),
+            ],
+        );
+        let mut files = Vec::new();
+        let text = preprocess(&format!("{}/main.glsl", dir), &[], &mut files).unwrap();
+        assert_eq!(
+            text,
+            "#version 330 core\n\
+             #line 1 1\n\
+             float x;\n\
+             #line 1 2\n\
+             float z;\n\
+             #line 3 1\n\
+             float y;\n\
+             #line 3 0\n\
+             void main() {}\n"
+        );
+        let names = ["main.glsl", "lib/common.glsl", "util.glsl"].map(|name| format!("{}/{}", dir, name));
+        assert_eq!(files, names);
+    }
+
+    #[test]
+    fn defines_follow_the_version() {
+        let dir = write_sources(
+            "defines",
+            &[("versioned.glsl", "// Header\n#version 330 core\nvoid main() {}\n"), ("bare.glsl", "void main() {}\n")],
+        );
+        let defines = ["INSTANCED".to_string(), "LIGHT_COUNT 2".to_string()];
+        let mut files = Vec::new();
+
+        let text = preprocess(&format!("{}/versioned.glsl", dir), &defines, &mut files).unwrap();
+        assert_eq!(
+            text,
+            "// Header\n#version 330 core\n#define INSTANCED\n#define LIGHT_COUNT 2\n#line 3 0\nvoid main() {}\n"
+        );
+
+        // Without a `#version` the defines come first.
+        let text = preprocess(&format!("{}/bare.glsl", dir), &defines, &mut files).unwrap();
+        assert_eq!(text, "#define INSTANCED\n#define LIGHT_COUNT 2\n#line 1 0\nvoid main() {}\n");
+    }
+}
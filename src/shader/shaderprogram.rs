@@ -2,120 +2,433 @@ use gl::types::GLuint;
 use gl::types::GLenum;
 use gl::types::GLchar;
 use gl::types::GLint;
+use gl::types::GLsizei;
+use nalgebra_glm as glm;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::fs::read;
-use std::io::{self, Read};
-use std::path::Path;
 use std::ptr;
-use std::ffi::{CStr, CString};
+use std::ffi::CString;
+use std::time::SystemTime;
 
-pub struct ShaderProgram {
-   shader_program : GLuint,
-   vertex_shader: GLuint,
-   geometry_shader: Option<GLuint>,
-   fragment_shader: GLuint,
+use super::preprocess::preprocess;
+use super::uniform_block;
+
+/// Step of building a program that can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+    Link,
 }
 
-impl ShaderProgram {
-    pub fn get_shader_program(&self) -> GLuint {
-        self.shader_program
+impl ShaderStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Link => "link",
+        }
     }
 
-    pub fn new(vertex_path: &str, geometry_path: Option<&str>, fragment_path: &str) -> Self {
-        let vertex_shader = ShaderProgram::loadShader( gl::VERTEX_SHADER, vertex_path);
-        let geometry_shader = geometry_path.map(|path| ShaderProgram::loadShader(gl::GEOMETRY_SHADER, path));
-        let fragment_shader = ShaderProgram::loadShader(gl::FRAGMENT_SHADER, fragment_path);
-
-        let shader_program = unsafe { gl::CreateProgram() };
-        unsafe {
-            gl::AttachShader(shader_program, vertex_shader);
-            if let Some(gs) = geometry_shader {
-                gl::AttachShader(shader_program, gs);
-            }
-            gl::AttachShader(shader_program, fragment_shader);
-            gl::LinkProgram(shader_program);
-
-            // Check for linking errors
-            let mut success = gl::FALSE as GLint;
-            gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                let mut len = 0;
-                gl::GetProgramiv(shader_program, gl::INFO_LOG_LENGTH, &mut len);
-                let error = create_whitespace_cstring_with_len(len as usize);
-                gl::GetProgramInfoLog(shader_program, len, ptr::null_mut(), error.as_ptr() as *mut GLchar);
-                panic!("Program linking failed: {}", error.to_string_lossy());
-            }
+    fn gl_type(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Link => unreachable!("linking has no shader object"),
         }
+    }
+}
+
+/// Why a shader program could not be built.
+#[derive(Clone, Debug)]
+pub struct ShaderError {
+    pub stage: ShaderStage,
+    /// Source file of the stage, or every file of the program for `Link`.
+    pub file: String,
+    /// First source line the driver's log points at, if it names one.
+    pub line: Option<u32>,
+    /// Driver info log, or why the file could not be read.
+    pub log: String,
+}
 
-        ShaderProgram {
-            shader_program: shader_program,
-            vertex_shader: vertex_shader,
-            geometry_shader: geometry_shader,
-            fragment_shader: fragment_shader,
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.stage, self.line) {
+            (ShaderStage::Link, _) => write!(f, "Failed to link {}: {}", self.file, self.log.trim_end()),
+            (stage, Some(line)) => write!(f, "{} shader {}:{}: {}", stage.name(), self.file, line, self.log.trim_end()),
+            (stage, None) => write!(f, "{} shader {}: {}", stage.name(), self.file, self.log.trim_end()),
         }
     }
+}
+
+impl std::error::Error for ShaderError {}
 
-    fn loadShader(shader_type : GLenum, file_name: &str) -> GLuint {
-        let shader_source = read_file(file_name);
-        let shader : GLuint;
+/// An active uniform, as the driver reports it.
+#[derive(Clone, Copy, Debug)]
+struct Uniform {
+    location: GLint,
+    /// GL type enum, e.g. `gl::FLOAT_MAT4`.
+    ty: GLenum,
+    /// Array length, 1 for anything else.
+    size: GLint,
+}
+
+/// The GL objects of one successful build, and what the driver says the
+/// program uses.
+struct Compiled {
+    shader_program: GLuint,
+    shaders: Vec<GLuint>,
+    uniforms: HashMap<String, Uniform>,
+}
 
+impl Drop for Compiled {
+    fn drop(&mut self) {
         unsafe {
-            shader = gl::CreateShader(shader_type);
-            let c_str = CString::new(shader_source).unwrap();
-            gl::ShaderSource(shader, 1, &c_str.as_ptr() , ptr::null() );
-            gl::CompileShader(shader);
-
-            let mut success = gl::FALSE as GLint;
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                let mut len = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-                let error = create_whitespace_cstring_with_len(len as usize);
-                gl::GetShaderInfoLog(shader, len, ptr::null_mut(), error.as_ptr() as *mut GLchar);
-                panic!("Shader compilation failed ({}): {}", file_name, error.to_string_lossy());
+            for &shader in &self.shaders {
+                gl::DetachShader(self.shader_program, shader);
+                gl::DeleteShader(shader);
             }
+            gl::DeleteProgram(self.shader_program);
         }
-        shader
+    }
+}
+
+/// A program built from GLSL files, which `reload_if_changed` rebuilds when
+/// the files, or any they include, change on disk.
+pub struct ShaderProgram {
+    compiled: Compiled,
+    sources: Vec<(ShaderStage, String)>,
+    /// Defined in every stage, see `preprocess`.
+    defines: Vec<String>,
+    /// Every file read by the last build attempt, includes too.
+    watched: Vec<String>,
+    /// Modification times of `watched` after the last build attempt.
+    modified: Vec<Option<SystemTime>>,
+    /// Why the last rebuild failed, while the previous program is still used.
+    error: Option<ShaderError>,
+    /// Uniforms already warned about, so each warning is printed once.
+    warned: RefCell<HashSet<String>>,
+}
+
+impl ShaderProgram {
+    pub fn new(vertex_path: &str, geometry_path: Option<&str>, fragment_path: &str) -> Result<Self, ShaderError> {
+        Self::with_defines(vertex_path, geometry_path, fragment_path, &[])
+    }
+
+    /// Builds a permutation of the files with `defines`, each the text after
+    /// `#define`, e.g. `"INSTANCED"`.
+    pub fn with_defines(
+        vertex_path: &str,
+        geometry_path: Option<&str>,
+        fragment_path: &str,
+        defines: &[&str],
+    ) -> Result<Self, ShaderError> {
+        let mut sources = vec![(ShaderStage::Vertex, vertex_path.to_string())];
+        sources.extend(geometry_path.map(|path| (ShaderStage::Geometry, path.to_string())));
+        sources.push((ShaderStage::Fragment, fragment_path.to_string()));
+        let defines: Vec<String> = defines.iter().map(|define| define.to_string()).collect();
+        let mut watched = Vec::new();
+        let compiled = build(&sources, &defines, &mut watched)?;
+        let modified = modified_times(&watched);
+        Ok(ShaderProgram { compiled, sources, defines, watched, modified, error: None, warned: RefCell::default() })
+    }
+
+    /// Rebuilds the program if any of its files changed since the last
+    /// attempt; returns whether it tried. A failed rebuild keeps the previous
+    /// program and is reported by `error` until a later one succeeds.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if modified_times(&self.watched) == self.modified {
+            return false;
+        }
+        let mut watched = Vec::new();
+        let result = build(&self.sources, &self.defines, &mut watched);
+        self.modified = modified_times(&watched);
+        self.watched = watched;
+        match result {
+            Ok(compiled) => {
+                self.compiled = compiled;
+                self.error = None;
+                self.warned.get_mut().clear();
+            },
+            Err(err) => self.error = Some(err),
+        }
+        true
+    }
+
+    /// The program's files, for messages.
+    pub fn files(&self) -> String {
+        join_files(&self.sources)
+    }
+
+    pub fn error(&self) -> Option<&ShaderError> {
+        self.error.as_ref()
     }
 
     pub fn use_program(&self) {
         unsafe {
-            gl::UseProgram(self.shader_program);
+            gl::UseProgram(self.compiled.shader_program);
+        }
+    }
+
+    // The setters below set uniforms of the program in use. They skip, and
+    // warn once about, names the program has no active uniform for and
+    // uniforms of another type.
+
+    pub fn set_f32(&self, name: &str, value: f32) {
+        if let Some(uniform) = self.uniform(name, gl::FLOAT, 1) {
+            unsafe { gl::Uniform1f(uniform.location, value) }
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: &glm::Vec2) {
+        if let Some(uniform) = self.uniform(name, gl::FLOAT_VEC2, 1) {
+            unsafe { gl::Uniform2fv(uniform.location, 1, value.as_ptr()) }
         }
     }
 
-    pub fn get_uniform_location(&self, name: &str) -> GLint {
-        let c_name = CString::new(name).unwrap();
-        unsafe { gl::GetUniformLocation(self.shader_program, c_name.as_ptr()) }
+    /// Sets the first `values.len()` elements of a `vec3` array.
+    pub fn set_vec3_array(&self, name: &str, values: &[glm::Vec3]) {
+        if let Some(uniform) = self.uniform(name, gl::FLOAT_VEC3, values.len()) {
+            unsafe { gl::Uniform3fv(uniform.location, values.len() as GLsizei, values.as_ptr() as *const f32) }
+        }
     }
 
-    pub fn get_attrib_location(&self, name: &str) -> GLint {
-        let c_name = CString::new(name).unwrap();
-        unsafe { gl::GetAttribLocation(self.shader_program, c_name.as_ptr()) }
+    pub fn set_vec4(&self, name: &str, value: &glm::Vec4) {
+        if let Some(uniform) = self.uniform(name, gl::FLOAT_VEC4, 1) {
+            unsafe { gl::Uniform4fv(uniform.location, 1, value.as_ptr()) }
+        }
     }
 
+    pub fn set_mat4(&self, name: &str, value: &glm::Mat4) {
+        if let Some(uniform) = self.uniform(name, gl::FLOAT_MAT4, 1) {
+            unsafe { gl::UniformMatrix4fv(uniform.location, 1, gl::FALSE, value.as_ptr()) }
+        }
+    }
 
+    /// Points a sampler of any kind at texture unit `unit`.
+    pub fn set_sampler(&self, name: &str, unit: u32) {
+        if let Some(uniform) = self.uniform(name, gl::SAMPLER_2D, 1) {
+            unsafe { gl::Uniform1i(uniform.location, unit as GLint) }
+        }
+    }
+
+    /// The uniform `name` if it is active, of type `ty` (any sampler type for
+    /// `SAMPLER_2D`) and holds at least `len` elements.
+    fn uniform(&self, name: &str, ty: GLenum, len: usize) -> Option<&Uniform> {
+        let problem = match self.compiled.uniforms.get(name) {
+            None => format!("{} has no active uniform {} (undeclared, or unused and optimised out)", self.files(), name),
+            Some(uniform) if !(uniform.ty == ty || ty == gl::SAMPLER_2D && is_sampler(uniform.ty)) => format!(
+                "uniform {} of {} is a {}, not a {}",
+                name,
+                self.files(),
+                glsl_type_name(uniform.ty),
+                glsl_type_name(ty)
+            ),
+            Some(uniform) if len > uniform.size as usize => {
+                format!("uniform {} of {} holds {} elements, not {}", name, self.files(), uniform.size, len)
+            },
+            Some(uniform) => return Some(uniform),
+        };
+        if self.warned.borrow_mut().insert(name.to_string()) {
+            eprintln!("Warning: {}", problem);
+        }
+        None
+    }
 }
 
-impl Drop for ShaderProgram {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DetachShader(self.shader_program, self.vertex_shader);
-            gl::DeleteShader(self.vertex_shader);
-            if let Some(gs) = self.geometry_shader {
-                gl::DetachShader(self.shader_program, gs);
-                gl::DeleteShader(gs);
+fn is_sampler(ty: GLenum) -> bool {
+    matches!(
+        ty,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+    )
+}
+
+/// GLSL name of a GL type enum, for messages.
+fn glsl_type_name(ty: GLenum) -> String {
+    let name = match ty {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::INT => "int",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        _ => return format!("type 0x{:x}", ty),
+    };
+    name.to_string()
+}
+
+/// Active uniforms of a linked program by name, without the `[0]` the
+/// driver appends to arrays. Uniforms in blocks have no location and are
+/// left out.
+unsafe fn active_uniforms(program: GLuint) -> HashMap<String, Uniform> {
+    let (mut n, mut capacity) = (0, 0);
+    let mut uniforms = HashMap::new();
+    unsafe {
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut n);
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut capacity);
+        let mut name = vec![0u8; capacity.max(1) as usize];
+        for index in 0..n as GLuint {
+            let (mut length, mut size, mut ty) = (0, 0, 0);
+            gl::GetActiveUniform(program, index, name.len() as GLsizei, &mut length, &mut size, &mut ty, name.as_mut_ptr() as *mut GLchar);
+            let location = gl::GetUniformLocation(program, name.as_ptr() as *const GLchar);
+            if location < 0 {
+                continue;
+            }
+            let full = String::from_utf8_lossy(&name[..length as usize]);
+            let key = full.strip_suffix("[0]").unwrap_or(&full).to_string();
+            uniforms.insert(key, Uniform { location, ty, size });
+        }
+    }
+    uniforms
+}
+
+fn modified_times(files: &[String]) -> Vec<Option<SystemTime>> {
+    files.iter().map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok()).collect()
+}
+
+fn join_files(sources: &[(ShaderStage, String)]) -> String {
+    let files: Vec<&str> = sources.iter().map(|(_, path)| path.as_str()).collect();
+    files.join(" + ")
+}
+
+/// Compiles and links `sources`, adding every file read to `watched`.
+fn build(sources: &[(ShaderStage, String)], defines: &[String], watched: &mut Vec<String>) -> Result<Compiled, ShaderError> {
+    let mut shaders = Vec::new();
+    for (stage, path) in sources {
+        let mut files = Vec::new();
+        let shader = load_shader(*stage, path, defines, &mut files);
+        for file in files {
+            if !watched.contains(&file) {
+                watched.push(file);
+            }
+        }
+        match shader {
+            Ok(shader) => shaders.push(shader),
+            Err(err) => {
+                unsafe {
+                    for shader in shaders {
+                        gl::DeleteShader(shader);
+                    }
+                }
+                return Err(err);
+            },
+        }
+    }
+
+    let shader_program = unsafe { gl::CreateProgram() };
+    // Owns the objects from here on, so they are deleted if linking fails.
+    let mut compiled = Compiled { shader_program, shaders, uniforms: HashMap::new() };
+    unsafe {
+        for &shader in &compiled.shaders {
+            gl::AttachShader(shader_program, shader);
+        }
+        gl::LinkProgram(shader_program);
+
+        // Check for linking errors
+        let mut success = gl::FALSE as GLint;
+        gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
+        if success != gl::TRUE as GLint {
+            let mut len = 0;
+            gl::GetProgramiv(shader_program, gl::INFO_LOG_LENGTH, &mut len);
+            let error = create_whitespace_cstring_with_len(len as usize);
+            gl::GetProgramInfoLog(shader_program, len, ptr::null_mut(), error.as_ptr() as *mut GLchar);
+            return Err(ShaderError {
+                stage: ShaderStage::Link,
+                file: join_files(sources),
+                line: None,
+                log: info_log(&error),
+            });
+        }
+
+        compiled.uniforms = active_uniforms(shader_program);
+    }
+    uniform_block::bind_blocks(shader_program).map_err(|log| ShaderError {
+        stage: ShaderStage::Link,
+        file: join_files(sources),
+        line: None,
+        log,
+    })?;
+    Ok(compiled)
+}
+
+/// Preprocesses and compiles one stage; `files` is filled as by `preprocess`.
+fn load_shader(stage: ShaderStage, file_name: &str, defines: &[String], files: &mut Vec<String>) -> Result<GLuint, ShaderError> {
+    let shader_source = preprocess(file_name, defines, files)
+        .map_err(|err| ShaderError { stage, file: err.file, line: err.line, log: err.message })?;
+    let c_str = CString::new(shader_source).map_err(|_| ShaderError {
+        stage,
+        file: file_name.to_string(),
+        line: None,
+        log: "Source contains a NUL byte".to_string(),
+    })?;
+
+    unsafe {
+        let shader = gl::CreateShader(stage.gl_type());
+        gl::ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut success = gl::FALSE as GLint;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+        if success != gl::TRUE as GLint {
+            let mut len = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+            let log = create_whitespace_cstring_with_len(len as usize);
+            gl::GetShaderInfoLog(shader, len, ptr::null_mut(), log.as_ptr() as *mut GLchar);
+            gl::DeleteShader(shader);
+            let log = info_log(&log);
+            // The driver names the file by its source string number, though
+            // Mesa reports some semantic errors against number 0.
+            let location = error_location(&log);
+            let file = location.and_then(|(number, _)| files.get(number)).map_or(file_name, |file| file.as_str());
+            return Err(ShaderError { stage, file: file.to_string(), line: location.map(|(_, line)| line), log });
+        }
+        Ok(shader)
+    }
+}
+
+/// Source string number and line of the first `<file>:<line>` or
+/// `<file>(<line>)` in a driver log, which covers Mesa (`0:12(5): error`),
+/// NVIDIA (`0(12) : error`) and AMD and Intel (`ERROR: 0:12: ...`).
+fn error_location(log: &str) -> Option<(usize, u32)> {
+    let bytes = log.as_bytes();
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let mut at = 0;
+    while at < bytes.len() {
+        let file = digits(at);
+        if file > 0 && at + file + 1 < bytes.len() && matches!(bytes[at + file], b':' | b'(') {
+            let start = at + file + 1;
+            let line = digits(start);
+            if line > 0 {
+                return Some((log[at..at + file].parse().ok()?, log[start..start + line].parse().ok()?));
             }
-            gl::DetachShader(self.shader_program, self.fragment_shader);
-            gl::DeleteShader(self.fragment_shader);
-            gl::DeleteProgram(self.shader_program);
         }
+        at += file.max(1);
     }
+    None
 }
 
-fn read_file(file_path: &str) -> String {
-    let bytes = read(Path::new(file_path)).expect(&format!("Failed to read file: {}", file_path));
-    String::from_utf8_lossy(&bytes).to_string()
+/// Text of a log filled in by the driver, without its terminating NUL.
+fn info_log(buffer: &CString) -> String {
+    buffer.to_string_lossy().trim_end_matches(['\0', ' ']).to_string()
 }
 
 fn create_whitespace_cstring_with_len(len: usize) -> CString {
@@ -0,0 +1,388 @@
+use gl::types::*;
+use nalgebra_glm as glm;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem::{offset_of, size_of};
+
+/// Binding point of `FrameBlock`, filled and bound once per frame.
+pub const FRAME_BINDING: GLuint = 0;
+/// Binding point of `MaterialBlock`, bound before drawing with a material.
+pub const MATERIAL_BINDING: GLuint = 1;
+/// Length of `lights` in `FrameBlock` and `MAX_LIGHTS` in common/frame.glsl.
+pub const MAX_LIGHTS: usize = 4;
+
+/// A `#[repr(C)]` struct laid out as a std140 uniform block of the shaders.
+/// Programs check their blocks against it when they are linked.
+pub trait UniformBlock: Copy {
+    /// Block name in GLSL.
+    const NAME: &'static str;
+    const BINDING: GLuint;
+
+    /// Byte offset of every member, by the name GL reports it under.
+    fn members() -> Vec<(String, usize)>;
+}
+
+/// A light in camera space, the `Light` struct of common/frame.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    /// Position with `w` 1, or with `w` 0 the direction the light shines in.
+    pub position: glm::Vec4,
+    /// Colour times intensity; alpha is unused.
+    pub color: glm::Vec4,
+}
+
+impl Light {
+    pub fn point(position: glm::Vec3, color: glm::Vec3) -> Self {
+        Light { position: glm::vec4(position.x, position.y, position.z, 1.0), color: glm::vec4(color.x, color.y, color.z, 1.0) }
+    }
+
+    pub fn directional(direction: glm::Vec3, color: glm::Vec3) -> Self {
+        Light { position: glm::vec4(direction.x, direction.y, direction.z, 0.0), color: glm::vec4(color.x, color.y, color.z, 1.0) }
+    }
+}
+
+/// Per-frame data shared by every program, the `Frame` block of
+/// common/frame.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FrameBlock {
+    pub projection: glm::Mat4,
+    pub view: glm::Mat4,
+    /// World-space camera position; `w` is unused.
+    pub camera_position: glm::Vec4,
+    /// Simulated seconds.
+    pub time: f32,
+    pub light_count: i32,
+    _padding: [f32; 2],
+    pub lights: [Light; MAX_LIGHTS],
+}
+
+impl FrameBlock {
+    /// The block for a camera with `projection` and `view`, lit by the first
+    /// `MAX_LIGHTS` of `lights`.
+    pub fn new(projection: glm::Mat4, view: glm::Mat4, time: f32, lights: &[Light]) -> Self {
+        let eye = glm::inverse(&view).column(3).into_owned();
+        let mut block = FrameBlock {
+            projection,
+            view,
+            camera_position: glm::vec4(eye.x, eye.y, eye.z, 1.0),
+            time,
+            light_count: lights.len().min(MAX_LIGHTS) as i32,
+            _padding: [0.0; 2],
+            lights: [Light::point(glm::Vec3::zeros(), glm::Vec3::zeros()); MAX_LIGHTS],
+        };
+        for (slot, light) in block.lights.iter_mut().zip(lights) {
+            *slot = *light;
+        }
+        block
+    }
+}
+
+impl UniformBlock for FrameBlock {
+    const NAME: &'static str = "Frame";
+    const BINDING: GLuint = FRAME_BINDING;
+
+    fn members() -> Vec<(String, usize)> {
+        let mut members = vec![
+            ("P".to_string(), offset_of!(FrameBlock, projection)),
+            ("V".to_string(), offset_of!(FrameBlock, view)),
+            ("cameraPosition".to_string(), offset_of!(FrameBlock, camera_position)),
+            ("time".to_string(), offset_of!(FrameBlock, time)),
+            ("lightCount".to_string(), offset_of!(FrameBlock, light_count)),
+        ];
+        for i in 0..MAX_LIGHTS {
+            let light = offset_of!(FrameBlock, lights) + i * size_of::<Light>();
+            members.push((format!("lights[{}].position", i), light + offset_of!(Light, position)));
+            members.push((format!("lights[{}].color", i), light + offset_of!(Light, color)));
+        }
+        members
+    }
+}
+
+/// How a surface reflects light, the `Material` block of
+/// common/material.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MaterialBlock {
+    /// Fraction of the base colour shown unlit.
+    pub ambient: f32,
+    /// Strength of highlights.
+    pub specular: f32,
+    /// Specular exponent.
+    pub shininess: f32,
+    _padding: f32,
+}
+
+impl MaterialBlock {
+    pub fn new(ambient: f32, specular: f32, shininess: f32) -> Self {
+        MaterialBlock { ambient, specular, shininess, _padding: 0.0 }
+    }
+}
+
+impl UniformBlock for MaterialBlock {
+    const NAME: &'static str = "Material";
+    const BINDING: GLuint = MATERIAL_BINDING;
+
+    fn members() -> Vec<(String, usize)> {
+        vec![
+            ("ambient".to_string(), offset_of!(MaterialBlock, ambient)),
+            ("specular".to_string(), offset_of!(MaterialBlock, specular)),
+            ("shininess".to_string(), offset_of!(MaterialBlock, shininess)),
+        ]
+    }
+}
+
+// std140 starts vec4s, mat4s, structs and arrays of structs on 16 bytes, and
+// rounds structs up to 16; the driver's offsets are checked at link time.
+const _: () = assert!(offset_of!(FrameBlock, camera_position) == 128);
+const _: () = assert!(offset_of!(FrameBlock, time) == 144);
+const _: () = assert!(offset_of!(FrameBlock, lights) == 160);
+const _: () = assert!(size_of::<Light>() == 32);
+const _: () = assert!(size_of::<FrameBlock>() == 160 + 32 * MAX_LIGHTS);
+const _: () = assert!(size_of::<MaterialBlock>() == 16);
+
+/// Size and member offsets a block of the shaders must have.
+struct Layout {
+    binding: GLuint,
+    size: usize,
+    members: HashMap<String, usize>,
+}
+
+impl Layout {
+    fn of<T: UniformBlock>() -> Self {
+        Layout { binding: T::BINDING, size: size_of::<T>(), members: T::members().into_iter().collect() }
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        if name == FrameBlock::NAME {
+            Some(Layout::of::<FrameBlock>())
+        } else if name == MaterialBlock::NAME {
+            Some(Layout::of::<MaterialBlock>())
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks every uniform block of a linked program against the struct of the
+/// same name, and binds it to that struct's binding point.
+pub(crate) fn bind_blocks(program: GLuint) -> Result<(), String> {
+    let (mut blocks, mut block_name_length, mut uniform_name_length) = (0, 0, 0);
+    unsafe {
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut blocks);
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH, &mut block_name_length);
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut uniform_name_length);
+    }
+    let mut name = vec![0u8; block_name_length.max(uniform_name_length).max(1) as usize];
+    let mut read_name = |read: &dyn Fn(GLsizei, *mut GLsizei, *mut GLchar)| {
+        let mut length = 0;
+        read(name.len() as GLsizei, &mut length, name.as_mut_ptr() as *mut GLchar);
+        String::from_utf8_lossy(&name[..length as usize]).to_string()
+    };
+
+    for block in 0..blocks as GLuint {
+        let block_name = read_name(&|size, length, name| unsafe {
+            gl::GetActiveUniformBlockName(program, block, size, length, name)
+        });
+        let layout = Layout::named(&block_name).ok_or_else(|| format!("Uniform block {} has no Rust struct", block_name))?;
+
+        let (mut data_size, mut count) = (0, 0);
+        unsafe {
+            gl::GetActiveUniformBlockiv(program, block, gl::UNIFORM_BLOCK_DATA_SIZE, &mut data_size);
+            gl::GetActiveUniformBlockiv(program, block, gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS, &mut count);
+        }
+        if data_size as usize > layout.size {
+            return Err(format!("Uniform block {} is {} bytes, its Rust struct {}", block_name, data_size, layout.size));
+        }
+        let mut indices = vec![0 as GLint; count as usize];
+        let mut offsets = vec![0 as GLint; count as usize];
+        unsafe {
+            gl::GetActiveUniformBlockiv(program, block, gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES, indices.as_mut_ptr());
+            gl::GetActiveUniformsiv(program, count, indices.as_ptr() as *const GLuint, gl::UNIFORM_OFFSET, offsets.as_mut_ptr());
+        }
+        for (&index, &offset) in indices.iter().zip(&offsets) {
+            let member = read_name(&|size, length, name| unsafe {
+                gl::GetActiveUniformName(program, index as GLuint, size, length, name)
+            });
+            match layout.members.get(&member) {
+                Some(&expected) if expected == offset as usize => {},
+                Some(&expected) => {
+                    return Err(format!(
+                        "{}.{} is at offset {} in the shader but {} in Rust",
+                        block_name, member, offset, expected
+                    ));
+                },
+                None => return Err(format!("{}.{} is not in its Rust struct", block_name, member)),
+            }
+        }
+        unsafe {
+            gl::UniformBlockBinding(program, block, layout.binding);
+        }
+    }
+    Ok(())
+}
+
+/// A GL buffer holding one `T`, for every program's block of that name.
+pub struct UniformBuffer<T: UniformBlock> {
+    buffer: GLuint,
+    block: PhantomData<T>,
+}
+
+impl<T: UniformBlock> UniformBuffer<T> {
+    pub fn new(value: &T) -> Self {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer);
+            gl::BufferData(gl::UNIFORM_BUFFER, size_of::<T>() as GLsizeiptr, value as *const T as *const _, gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+        UniformBuffer { buffer, block: PhantomData }
+    }
+
+    pub fn update(&self, value: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer);
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, size_of::<T>() as GLsizeiptr, value as *const T as *const _);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    /// Makes this the `T` of every program until another is bound.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, T::BINDING, self.buffer);
+        }
+    }
+}
+
+impl<T: UniformBlock> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Declarations of a GLSL file, read just far enough to lay out its
+    /// uniform blocks by the std140 rules.
+    struct Glsl {
+        defines: HashMap<String, usize>,
+        /// Members of every struct and block, as (type, name, array length).
+        declarations: HashMap<String, Vec<(String, String, Option<usize>)>>,
+    }
+
+    impl Glsl {
+        fn read(path: &str) -> Self {
+            let source = fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+            let source: String = source.lines().map(|line| line.split("//").next().unwrap()).collect::<Vec<_>>().join("\n");
+            let mut glsl = Glsl { defines: HashMap::new(), declarations: HashMap::new() };
+            for line in source.lines() {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                if let ["#define", name, value] = words[..]
+                    && let Ok(value) = value.parse()
+                {
+                    glsl.defines.insert(name.to_string(), value);
+                }
+            }
+            let mut rest = source.as_str();
+            while let Some(open) = rest.find('{') {
+                let close = open + rest[open..].find('}').unwrap();
+                let name = rest[..open].split_whitespace().last().unwrap().to_string();
+                let members = rest[open + 1..close]
+                    .split(';')
+                    .filter(|member| !member.trim().is_empty())
+                    .map(|member| glsl.member(member))
+                    .collect();
+                glsl.declarations.insert(name, members);
+                rest = &rest[close + 1..];
+            }
+            glsl
+        }
+
+        fn member(&self, declaration: &str) -> (String, String, Option<usize>) {
+            let words: Vec<&str> = declaration.split_whitespace().collect();
+            let (ty, name) = (words[words.len() - 2], words[words.len() - 1]);
+            match name.split_once('[') {
+                Some((name, length)) => {
+                    let length = length.trim_end_matches(']');
+                    let length = self.defines.get(length).copied().unwrap_or_else(|| length.parse().unwrap());
+                    (ty.to_string(), name.to_string(), Some(length))
+                },
+                None => (ty.to_string(), name.to_string(), None),
+            }
+        }
+
+        /// std140 base alignment and size of `ty`.
+        fn alignment_and_size(&self, ty: &str) -> (usize, usize) {
+            match ty {
+                "float" | "int" | "uint" | "bool" => (4, 4),
+                "vec2" => (8, 8),
+                "vec3" => (16, 12),
+                "vec4" => (16, 16),
+                "mat4" => (16, 64),
+                _ => {
+                    let (mut members, mut offset) = (Vec::new(), 0);
+                    self.lay_out(ty, "", 0, &mut members, &mut offset);
+                    (16, offset.next_multiple_of(16))
+                },
+            }
+        }
+
+        /// Appends the offset of every basic member of the struct or block
+        /// `name`, laid out from `offset`, with GL's names for them.
+        fn lay_out(&self, name: &str, prefix: &str, base: usize, members: &mut Vec<(String, usize)>, offset: &mut usize) {
+            for (ty, member, length) in &self.declarations[name] {
+                let (alignment, size) = self.alignment_and_size(ty);
+                let is_struct = self.declarations.contains_key(ty);
+                let names: Vec<String> = match length {
+                    Some(length) => (0..*length).map(|i| format!("{}{}[{}]", prefix, member, i)).collect(),
+                    None => vec![format!("{}{}", prefix, member)],
+                };
+                // Array elements are rounded up to 16 bytes apiece.
+                let (alignment, stride) = if length.is_some() { (16, size.next_multiple_of(16)) } else { (alignment, size) };
+                *offset = offset.next_multiple_of(alignment);
+                for name in names {
+                    if is_struct {
+                        let mut inner = 0;
+                        self.lay_out(ty, &format!("{}.", name), base + *offset, members, &mut inner);
+                    } else {
+                        members.push((name, base + *offset));
+                    }
+                    *offset += stride;
+                }
+            }
+        }
+
+        /// Offsets of the members of the block of `T`, and the block's size.
+        fn block<T: UniformBlock>(&self) -> (HashMap<String, usize>, usize) {
+            let (mut members, mut offset) = (Vec::new(), 0);
+            self.lay_out(T::NAME, "", 0, &mut members, &mut offset);
+            (members.into_iter().collect(), offset)
+        }
+    }
+
+    fn assert_matches<T: UniformBlock>(path: &str) {
+        let (offsets, size) = Glsl::read(path).block::<T>();
+        let expected: HashMap<String, usize> = T::members().into_iter().collect();
+        assert_eq!(offsets, expected, "{} in {} is laid out differently from its Rust struct", T::NAME, path);
+        assert!(size <= size_of::<T>(), "{} in {} is {} bytes, its Rust struct {}", T::NAME, path, size, size_of::<T>());
+    }
+
+    #[test]
+    fn frame_block_matches_frame_glsl() {
+        assert_matches::<FrameBlock>("assets/shaders/common/frame.glsl");
+        assert_eq!(Glsl::read("assets/shaders/common/frame.glsl").defines["MAX_LIGHTS"], MAX_LIGHTS);
+    }
+
+    #[test]
+    fn material_block_matches_material_glsl() {
+        assert_matches::<MaterialBlock>("assets/shaders/common/material.glsl");
+    }
+}
@@ -0,0 +1,95 @@
+use nalgebra_glm as glm;
+
+use super::caste::Caste;
+use super::foraging::ForageState;
+use super::rng::SimRng;
+
+/// Axis the ant mesh points along in model space.
+pub const ANT_FORWARD: glm::Vec3 = glm::Vec3::new(0.0, 0.0, 1.0);
+pub const ANT_UP: glm::Vec3 = glm::Vec3::new(0.0, 1.0, 0.0);
+
+#[derive(Clone, Debug)]
+pub struct Ant {
+    pub id: u32,
+    pub caste: Caste,
+    pub position: glm::Vec3,
+    pub velocity: glm::Vec3,
+    pub orientation: glm::Quat,
+    pub energy: f32,
+    /// Seconds since the ant was spawned.
+    pub age: f32,
+    /// Roll around the forward axis in radians, positive rolls right.
+    pub bank: f32,
+    /// Unit vector on the wander sphere, see `steering::Wander`.
+    pub wander_dir: glm::Vec3,
+    /// Point the ant is currently heading for, if any.
+    pub target: Option<glm::Vec3>,
+    /// Position of the closest sensed ant of another colony, which `Flee`
+    /// runs from, as of the last step.
+    pub threat: Option<glm::Vec3>,
+    /// Id of the nest the ant belongs to.
+    pub nest: Option<u32>,
+    pub state: ForageState,
+    /// Food currently carried.
+    pub carrying: f32,
+    /// Where the ant last picked up food.
+    pub food_memory: Option<glm::Vec3>,
+    /// The ant's own random stream, see `rng::ant_stream`.
+    pub rng: SimRng,
+}
+
+impl Ant {
+    pub fn new(id: u32, caste: Caste, position: glm::Vec3, velocity: glm::Vec3, rng: SimRng) -> Self {
+        let mut ant = Ant {
+            id,
+            caste,
+            position,
+            velocity,
+            orientation: glm::quat_identity(),
+            energy: 1.0,
+            age: 0.0,
+            bank: 0.0,
+            wander_dir: ANT_FORWARD,
+            target: None,
+            threat: None,
+            nest: None,
+            state: ForageState::Searching,
+            carrying: 0.0,
+            food_memory: None,
+            rng,
+        };
+        ant.face_velocity();
+        ant
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.energy > 0.0
+    }
+
+    pub fn forward(&self) -> glm::Vec3 {
+        glm::quat_rotate_vec3(&self.orientation, &ANT_FORWARD)
+    }
+
+    pub fn up(&self) -> glm::Vec3 {
+        glm::quat_rotate_vec3(&self.orientation, &ANT_UP)
+    }
+
+    /// Rotates the ant so that its forward axis points along its velocity,
+    /// rolled by `bank`.
+    pub fn face_velocity(&mut self) {
+        if glm::length2(&self.velocity) < 1e-8 {
+            return;
+        }
+        let dir = glm::normalize(&self.velocity);
+        // Pick another up vector when flying (almost) straight up or down.
+        let up = if glm::dot(&dir, &ANT_UP).abs() > 0.999 { ANT_FORWARD } else { ANT_UP };
+        // `quat_look_at_lh` is the inverse of the rotation taking +Z onto `dir`.
+        let heading = glm::quat_conjugate(&glm::quat_look_at_lh(&dir, &up));
+        self.orientation = heading * glm::quat_angle_axis(self.bank, &ANT_FORWARD);
+    }
+
+    /// Model matrix (translation * rotation) of the ant, without any mesh scaling.
+    pub fn transform(&self) -> glm::Mat4 {
+        glm::translate(&glm::identity(), &self.position) * glm::quat_to_mat4(&self.orientation)
+    }
+}
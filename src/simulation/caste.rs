@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::pheromone::Channel;
+use super::steering::SteeringWeights;
+
+pub const CASTE_PROFILES_PATH: &str = "assets/data/castes.toml";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Caste {
+    Worker,
+    Forager,
+    Soldier,
+    Queen,
+    Scout,
+}
+
+impl Caste {
+    pub const ALL: [Caste; 5] = [Caste::Worker, Caste::Forager, Caste::Soldier, Caste::Queen, Caste::Scout];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Caste::Worker => "worker",
+            Caste::Forager => "forager",
+            Caste::Soldier => "soldier",
+            Caste::Queen => "queen",
+            Caste::Scout => "scout",
+        }
+    }
+}
+
+/// How strongly an ant reacts to each pheromone channel (0 ignores it).
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PheromoneSensitivity {
+    pub trail: f32,
+    pub alarm: f32,
+    pub recruitment: f32,
+    pub home: f32,
+}
+
+impl PheromoneSensitivity {
+    pub fn get(&self, channel: Channel) -> f32 {
+        match channel {
+            Channel::Trail => self.trail,
+            Channel::Alarm => self.alarm,
+            Channel::Recruitment => self.recruitment,
+            Channel::Home => self.home,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CasteProfile {
+    /// World units per second.
+    pub max_speed: f32,
+    /// World units per second squared.
+    pub max_acceleration: f32,
+    /// Radians per second.
+    pub turn_rate: f32,
+    /// Largest roll into a turn, in radians.
+    pub max_bank: f32,
+    pub sensing_radius: f32,
+    pub sensitivity: PheromoneSensitivity,
+    /// Units of food carried per trip.
+    pub carrying_capacity: f32,
+    /// Seconds until the ant dies of old age.
+    pub lifespan: f32,
+    /// Energy lost per second (ants start with 1.0).
+    pub energy_burn_rate: f32,
+    pub steering: SteeringWeights,
+}
+
+impl CasteProfile {
+    pub fn default_for(caste: Caste) -> Self {
+        let sensitivity = |trail, alarm, recruitment, home| PheromoneSensitivity { trail, alarm, recruitment, home };
+        let steering = |wander, pheromone, separation, alignment, cohesion| SteeringWeights {
+            seek: 1.0,
+            flee: 2.0,
+            wander,
+            pheromone,
+            avoid_obstacles: 3.0,
+            separation,
+            alignment,
+            cohesion,
+        };
+        match caste {
+            Caste::Worker => CasteProfile {
+                max_speed: 0.6,
+                max_acceleration: 1.5,
+                turn_rate: 2.5,
+                max_bank: 0.6,
+                sensing_radius: 0.75,
+                sensitivity: sensitivity(1.0, 0.5, 1.0, 0.5),
+                carrying_capacity: 1.0,
+                lifespan: 600.0,
+                energy_burn_rate: 0.002,
+                steering: steering(1.0, 1.0, 0.8, 0.3, 0.2),
+            },
+            Caste::Forager => CasteProfile {
+                max_speed: 0.8,
+                max_acceleration: 2.0,
+                turn_rate: 3.0,
+                max_bank: 0.6,
+                sensing_radius: 1.0,
+                sensitivity: sensitivity(1.0, 0.3, 1.0, 1.0),
+                carrying_capacity: 2.0,
+                lifespan: 400.0,
+                energy_burn_rate: 0.003,
+                steering: steering(1.0, 1.5, 0.8, 0.2, 0.1),
+            },
+            Caste::Soldier => CasteProfile {
+                max_speed: 0.7,
+                max_acceleration: 2.0,
+                turn_rate: 2.0,
+                max_bank: 0.7,
+                sensing_radius: 1.0,
+                sensitivity: sensitivity(0.2, 1.5, 0.2, 0.5),
+                carrying_capacity: 0.0,
+                lifespan: 500.0,
+                energy_burn_rate: 0.004,
+                steering: steering(0.8, 1.0, 1.0, 0.6, 0.5),
+            },
+            Caste::Queen => CasteProfile {
+                max_speed: 0.1,
+                max_acceleration: 0.2,
+                turn_rate: 0.5,
+                max_bank: 0.2,
+                sensing_radius: 0.5,
+                sensitivity: sensitivity(0.0, 0.5, 0.0, 1.0),
+                carrying_capacity: 0.0,
+                lifespan: 6000.0,
+                energy_burn_rate: 0.0005,
+                steering: steering(0.3, 0.0, 1.0, 0.0, 0.0),
+            },
+            Caste::Scout => CasteProfile {
+                max_speed: 1.2,
+                max_acceleration: 3.0,
+                turn_rate: 4.0,
+                max_bank: 0.9,
+                sensing_radius: 1.5,
+                sensitivity: sensitivity(0.1, 0.5, 0.0, 0.3),
+                carrying_capacity: 0.5,
+                lifespan: 300.0,
+                energy_burn_rate: 0.004,
+                steering: steering(1.5, 0.2, 0.6, 0.0, 0.0),
+            },
+        }
+    }
+}
+
+/// Balance values for every caste, indexed by `Caste`.
+#[derive(Clone, Debug)]
+pub struct CasteTable {
+    profiles: [CasteProfile; Caste::ALL.len()],
+}
+
+impl Default for CasteTable {
+    fn default() -> Self {
+        CasteTable { profiles: Caste::ALL.map(CasteProfile::default_for) }
+    }
+}
+
+impl CasteTable {
+    pub fn get(&self, caste: Caste) -> &CasteProfile {
+        &self.profiles[caste.index()]
+    }
+
+    pub fn get_mut(&mut self, caste: Caste) -> &mut CasteProfile {
+        &mut self.profiles[caste.index()]
+    }
+
+    /// Parses a TOML table keyed by caste name. Castes missing from the file
+    /// keep their built-in defaults.
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let parsed: HashMap<Caste, CasteProfile> = toml::from_str(source).map_err(|e| e.to_string())?;
+        let mut table = CasteTable::default();
+        for (caste, profile) in parsed {
+            table.profiles[caste.index()] = profile;
+        }
+        Ok(table)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_toml(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Loads `CASTE_PROFILES_PATH`, falling back to the defaults if it is missing or invalid.
+    pub fn load_or_default() -> Self {
+        Self::load(CASTE_PROFILES_PATH).unwrap_or_else(|err| {
+            eprintln!("Using default caste profiles: {}", err);
+            CasteTable::default()
+        })
+    }
+}
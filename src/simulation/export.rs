@@ -0,0 +1,409 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::ant::Ant;
+use super::caste::Caste;
+use super::foraging::ForageState;
+use super::pheromone::Channel;
+use super::stats::{Sample, Stats};
+use super::world::World;
+
+/// First bytes of every binary export file.
+const MAGIC: &[u8; 8] = b"ANTCOLS\0";
+/// Version written into the header of every export. Bump it whenever a
+/// column is added, removed, renamed or changes type, or the binary layout
+/// below changes, so readers can tell.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+/// Rows buffered per column before a binary block is written.
+const BLOCK_ROWS: usize = 4096;
+
+const CASTE_NAMES: [&str; Caste::ALL.len()] = {
+    let mut names = [""; Caste::ALL.len()];
+    let mut i = 0;
+    while i < names.len() {
+        names[i] = Caste::ALL[i].name();
+        i += 1;
+    }
+    names
+};
+const STATE_NAMES: [&str; 3] = ["searching", "returning", "recruiting"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A `#` line with the schema, a header row, then one row per line.
+    Csv,
+    /// Little endian columnar blocks, see `Table`.
+    Binary,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "csv" => Ok(ExportFormat::Csv),
+            "binary" => Ok(ExportFormat::Binary),
+            _ => Err(format!("Unknown export format {:?}, expected csv or binary", name)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Binary => "antcols",
+        }
+    }
+}
+
+/// What an `Exporter` writes.
+#[derive(Clone, Debug)]
+pub struct ExportConfig {
+    pub format: ExportFormat,
+    /// Ticks between samples. The last tick of a run always gets one.
+    pub every: u64,
+    /// Columns to write, of either table; empty for all of them. `tick`, and
+    /// `id` for trajectories, are always written.
+    pub columns: Vec<String>,
+    /// Also write every ant at every sample to `trajectories`.
+    pub trajectories: bool,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig { format: ExportFormat::Csv, every: 60, columns: Vec::new(), trajectories: false }
+    }
+}
+
+/// Type of the values of a column. Categories are written as their name in
+/// CSV and as a u8 index into the names in binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    U32,
+    U64,
+    F32,
+    F64,
+    Category(&'static [&'static str]),
+}
+
+impl ColumnType {
+    fn tag(self) -> u8 {
+        match self {
+            ColumnType::U32 => 0,
+            ColumnType::U64 => 1,
+            ColumnType::F32 => 2,
+            ColumnType::F64 => 3,
+            ColumnType::Category(_) => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Value {
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Category(u8),
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub name: String,
+    pub ty: ColumnType,
+}
+
+fn column(name: impl Into<String>, ty: ColumnType) -> Column {
+    Column { name: name.into(), ty }
+}
+
+/// Columns of the `stats` table, one row per sample.
+pub fn stats_columns() -> Vec<Column> {
+    let mut columns = vec![column("tick", ColumnType::U64), column("time", ColumnType::F64), column("ants", ColumnType::U32)];
+    columns.extend(Caste::ALL.map(|caste| column(caste.name(), ColumnType::U32)));
+    columns.extend(STATE_NAMES.map(|state| column(state, ColumnType::U32)));
+    columns.extend([
+        column("delivered", ColumnType::F32),
+        column("trips", ColumnType::U32),
+        column("recent_income", ColumnType::F32),
+        column("nest_stock", ColumnType::F32),
+        column("food_stock", ColumnType::F32),
+    ]);
+    columns.extend(Channel::ALL.map(|channel| column(format!("{}_mass", channel.name()), ColumnType::F32)));
+    columns.extend([
+        column("average_energy", ColumnType::F32),
+        column("births", ColumnType::U32),
+        column("deaths", ColumnType::U32),
+    ]);
+    columns
+}
+
+fn state_index(state: ForageState) -> usize {
+    match state {
+        ForageState::Searching => 0,
+        ForageState::Returning => 1,
+        ForageState::Recruiting { .. } => 2,
+    }
+}
+
+fn stats_row(world: &World, sample: &Sample) -> Vec<Value> {
+    let mut states = [0u32; STATE_NAMES.len()];
+    for ant in &world.ants {
+        states[state_index(ant.state)] += 1;
+    }
+    let mut row = vec![Value::U64(world.tick), Value::F64(world.time), Value::U32(world.ants.len() as u32)];
+    row.extend(sample.population.map(Value::U32));
+    row.extend(states.map(Value::U32));
+    row.extend([
+        Value::F32(world.foraging.delivered),
+        Value::U32(world.foraging.trips),
+        Value::F32(world.foraging.recent_income),
+        Value::F32(world.nests.iter().map(|nest| nest.stock).sum()),
+        Value::F32(world.food_sources.iter().map(|food| food.stock).sum()),
+    ]);
+    row.extend(sample.pheromone_mass.map(Value::F32));
+    row.extend([Value::F32(sample.average_energy), Value::U32(sample.births), Value::U32(sample.deaths)]);
+    row
+}
+
+/// Columns of the `trajectories` table, one row per ant per sample.
+pub fn ant_columns() -> Vec<Column> {
+    let mut columns = vec![
+        column("tick", ColumnType::U64),
+        column("id", ColumnType::U32),
+        column("caste", ColumnType::Category(&CASTE_NAMES)),
+        column("state", ColumnType::Category(&STATE_NAMES)),
+    ];
+    columns.extend(["x", "y", "z", "vx", "vy", "vz", "energy", "age", "carrying"].map(|name| column(name, ColumnType::F32)));
+    columns
+}
+
+fn ant_row(world: &World, ant: &Ant) -> Vec<Value> {
+    let (p, v) = (ant.position, ant.velocity);
+    vec![
+        Value::U64(world.tick),
+        Value::U32(ant.id),
+        Value::Category(ant.caste.index() as u8),
+        Value::Category(state_index(ant.state) as u8),
+        Value::F32(p.x),
+        Value::F32(p.y),
+        Value::F32(p.z),
+        Value::F32(v.x),
+        Value::F32(v.y),
+        Value::F32(v.z),
+        Value::F32(ant.energy),
+        Value::F32(ant.age),
+        Value::F32(ant.carrying),
+    ]
+}
+
+/// One output file.
+///
+/// Binary files start with `MAGIC`, the u32 schema version, the table name
+/// and the u32 column count, followed by each column's name, u8 type tag
+/// (0 u32, 1 u64, 2 f32, 3 f64, 4 category) and, for categories, the u32
+/// count and names of the categories. Blocks follow until the end of the
+/// file: a u32 row count, then each column's values for those rows back to
+/// back. Strings are a u32 byte length and UTF-8; everything is little endian.
+struct Table {
+    path: PathBuf,
+    out: BufWriter<File>,
+    format: ExportFormat,
+    /// Indices of the written columns in the full row.
+    keep: Vec<usize>,
+    columns: Vec<Column>,
+    /// Values per written column of the rows not yet in a binary block.
+    block: Vec<Vec<Value>>,
+}
+
+impl Table {
+    fn create(dir: &Path, name: &str, format: ExportFormat, all: Vec<Column>, keep: Vec<usize>) -> Result<Self, String> {
+        let path = dir.join(format!("{}.{}", name, format.extension()));
+        let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let columns: Vec<Column> = keep.iter().map(|&i| all[i].clone()).collect();
+        let mut table =
+            Table { path, out: BufWriter::new(file), format, keep, block: vec![Vec::new(); columns.len()], columns };
+        table.write_header(name).map_err(|e| table.write_error(e))?;
+        Ok(table)
+    }
+
+    fn write_header(&mut self, name: &str) -> std::io::Result<()> {
+        let out = &mut self.out;
+        match self.format {
+            ExportFormat::Csv => {
+                writeln!(out, "# schema={} table={}", EXPORT_SCHEMA_VERSION, name)?;
+                let names: Vec<&str> = self.columns.iter().map(|column| column.name.as_str()).collect();
+                writeln!(out, "{}", names.join(","))
+            },
+            ExportFormat::Binary => {
+                out.write_all(MAGIC)?;
+                out.write_all(&EXPORT_SCHEMA_VERSION.to_le_bytes())?;
+                write_str(out, name)?;
+                out.write_all(&(self.columns.len() as u32).to_le_bytes())?;
+                for column in &self.columns {
+                    write_str(out, &column.name)?;
+                    out.write_all(&[column.ty.tag()])?;
+                    if let ColumnType::Category(names) = column.ty {
+                        out.write_all(&(names.len() as u32).to_le_bytes())?;
+                        for name in names {
+                            write_str(out, name)?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn push(&mut self, row: &[Value]) -> Result<(), String> {
+        match self.format {
+            ExportFormat::Csv => self.write_csv_row(row).map_err(|e| self.write_error(e)),
+            ExportFormat::Binary => {
+                for (values, &i) in self.block.iter_mut().zip(&self.keep) {
+                    values.push(row[i]);
+                }
+                if self.block[0].len() >= BLOCK_ROWS {
+                    self.write_block().map_err(|e| self.write_error(e))?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn write_csv_row(&mut self, row: &[Value]) -> std::io::Result<()> {
+        for (n, (&i, column)) in self.keep.iter().zip(&self.columns).enumerate() {
+            if n > 0 {
+                self.out.write_all(b",")?;
+            }
+            match (row[i], column.ty) {
+                (Value::U32(value), _) => write!(self.out, "{}", value)?,
+                (Value::U64(value), _) => write!(self.out, "{}", value)?,
+                (Value::F32(value), _) => write!(self.out, "{}", value)?,
+                (Value::F64(value), _) => write!(self.out, "{}", value)?,
+                (Value::Category(index), ColumnType::Category(names)) => write!(self.out, "{}", names[index as usize])?,
+                (Value::Category(index), _) => write!(self.out, "{}", index)?,
+            }
+        }
+        writeln!(self.out)
+    }
+
+    fn write_block(&mut self) -> std::io::Result<()> {
+        let rows = self.block[0].len();
+        if rows == 0 {
+            return Ok(());
+        }
+        self.out.write_all(&(rows as u32).to_le_bytes())?;
+        for values in &mut self.block {
+            for value in values.drain(..) {
+                match value {
+                    Value::U32(value) => self.out.write_all(&value.to_le_bytes())?,
+                    Value::U64(value) => self.out.write_all(&value.to_le_bytes())?,
+                    Value::F32(value) => self.out.write_all(&value.to_le_bytes())?,
+                    Value::F64(value) => self.out.write_all(&value.to_le_bytes())?,
+                    Value::Category(index) => self.out.write_all(&[index])?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.write_block().and_then(|()| self.out.flush()).map_err(|e| self.write_error(e))
+    }
+
+    fn write_error(&self, err: std::io::Error) -> String {
+        format!("Failed to write {}: {}", self.path.display(), err)
+    }
+}
+
+fn write_str<W: Write>(out: &mut W, text: &str) -> std::io::Result<()> {
+    out.write_all(&(text.len() as u32).to_le_bytes())?;
+    out.write_all(text.as_bytes())
+}
+
+/// Indices of the columns of `all` that `selected` asks for, plus the `key`
+/// columns.
+fn select(all: &[Column], selected: &[String], key: &[&str]) -> Vec<usize> {
+    (0..all.len())
+        .filter(|&i| {
+            let name = all[i].name.as_str();
+            selected.is_empty() || key.contains(&name) || selected.iter().any(|selected| selected == name)
+        })
+        .collect()
+}
+
+/// Writes samples of a world to `stats` and, if asked, `trajectories` in a
+/// directory, for analysis outside the simulator. Used by headless runs and
+/// by the windowed app alike.
+pub struct Exporter {
+    dir: PathBuf,
+    stats: Table,
+    trajectories: Option<Table>,
+    recorder: Stats,
+}
+
+impl Exporter {
+    /// Creates `dir` and the files in it, checking the selected columns exist.
+    pub fn create(dir: &Path, config: &ExportConfig) -> Result<Self, String> {
+        let stats_columns = stats_columns();
+        let ant_columns = ant_columns();
+        for name in &config.columns {
+            let known = |columns: &[Column]| columns.iter().any(|column| &column.name == name);
+            if !(known(&stats_columns) || config.trajectories && known(&ant_columns)) {
+                return Err(format!("Unknown export column {:?}", name));
+            }
+        }
+
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let keep = select(&stats_columns, &config.columns, &["tick"]);
+        let stats = Table::create(dir, "stats", config.format, stats_columns, keep)?;
+        let trajectories = if config.trajectories {
+            let keep = select(&ant_columns, &config.columns, &["tick", "id"]);
+            Some(Table::create(dir, "trajectories", config.format, ant_columns, keep)?)
+        } else {
+            None
+        };
+        Ok(Exporter { dir: dir.to_path_buf(), stats, trajectories, recorder: Stats::new(1, config.every) })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes a sample if `world` is at least the sampling interval past the
+    /// last one; can be called every tick.
+    pub fn record(&mut self, world: &World) -> Result<(), String> {
+        if self.recorder.record(world) {
+            self.write(world)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a sample of `world` now.
+    pub fn sample(&mut self, world: &World) -> Result<(), String> {
+        self.recorder.sample(world);
+        self.write(world)
+    }
+
+    fn write(&mut self, world: &World) -> Result<(), String> {
+        let sample = self.recorder.latest().unwrap();
+        self.stats.push(&stats_row(world, sample))?;
+        if let Some(trajectories) = self.trajectories.as_mut() {
+            for ant in &world.ants {
+                trajectories.push(&ant_row(world, ant))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples `world` unless that tick was the last one written, and
+    /// flushes everything to disk. Returns the counters of the whole export.
+    pub fn finish(mut self, world: &World) -> Result<Stats, String> {
+        if self.recorder.latest().is_none_or(|sample| sample.tick != world.tick) {
+            self.sample(world)?;
+        }
+        self.stats.finish()?;
+        if let Some(trajectories) = self.trajectories {
+            trajectories.finish()?;
+        }
+        Ok(self.recorder)
+    }
+}
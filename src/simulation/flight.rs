@@ -0,0 +1,106 @@
+use nalgebra_glm as glm;
+
+use super::ant::Ant;
+use super::caste::CasteProfile;
+
+/// How quickly the bank angle follows its target, per second.
+const BANK_RESPONSE: f32 = 4.0;
+
+/// Applies a steering acceleration to `ant` for one tick, respecting the
+/// caste's acceleration, speed and turn-rate limits, and banks into turns.
+pub fn fly(ant: &mut Ant, profile: &CasteProfile, steering: glm::Vec3, dt: f32) {
+    let accel = glm::length(&steering);
+    let steering = if accel > profile.max_acceleration {
+        steering * (profile.max_acceleration / accel)
+    } else {
+        steering
+    };
+
+    let desired = ant.velocity + steering * dt;
+    let speed = glm::length(&desired).min(profile.max_speed);
+
+    let forward = ant.forward();
+    let mut turn_rate = 0.0;
+    let heading = if glm::length2(&desired) > 1e-12 {
+        let target = glm::normalize(&desired);
+        let angle = glm::dot(&forward, &target).clamp(-1.0, 1.0).acos();
+        let max_angle = profile.turn_rate * dt;
+        if angle <= max_angle {
+            turn_rate = angle / dt;
+            target
+        } else {
+            let mut axis = glm::cross(&forward, &target);
+            if glm::length2(&axis) < 1e-12 {
+                // Target is straight behind; turn over the ant's own up axis.
+                axis = ant.up();
+            }
+            let axis = glm::normalize(&axis);
+            turn_rate = profile.turn_rate;
+            glm::rotate_vec3(&forward, max_angle, &axis)
+        }
+    } else {
+        forward
+    };
+
+    // Bank towards the inside of the turn, proportionally to how hard we turn.
+    let turn_side = glm::dot(&glm::cross(&forward, &heading), &ant.up()).signum();
+    let bank_target = if profile.turn_rate > 0.0 {
+        -turn_side * (turn_rate / profile.turn_rate).min(1.0) * profile.max_bank
+    } else {
+        0.0
+    };
+    ant.bank += (bank_target - ant.bank) * (BANK_RESPONSE * dt).min(1.0);
+
+    ant.velocity = heading * speed;
+    ant.position += ant.velocity * dt;
+    ant.face_velocity();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::caste::Caste;
+    use crate::simulation::rng::{random_in_sphere, random_on_sphere, substream};
+
+    /// Slack for float error; `acos` near 1 is the loosest of the checks.
+    const EPSILON: f32 = 1e-3;
+
+    #[test]
+    fn fly_respects_the_caste_limits() {
+        let mut rng = substream(7, 0);
+        for caste in Caste::ALL {
+            let profile = CasteProfile::default_for(caste);
+            for dt in [1.0 / 60.0, 0.1] {
+                let velocity = random_in_sphere(&mut rng, profile.max_speed);
+                let mut ant = Ant::new(0, caste, glm::Vec3::zeros(), velocity, substream(7, 1));
+                for _ in 0..500 {
+                    // Far more than the ant may apply, in a new direction every tick.
+                    let steering = random_on_sphere(&mut rng, 20.0 * profile.max_acceleration);
+                    let (velocity, forward) = (ant.velocity, ant.forward());
+                    fly(&mut ant, &profile, steering, dt);
+
+                    let speed = glm::length(&ant.velocity);
+                    let accel = glm::distance(&ant.velocity, &velocity) / dt;
+                    let turn = glm::dot(&forward, &ant.forward()).clamp(-1.0, 1.0).acos();
+                    assert!(speed <= profile.max_speed * (1.0 + EPSILON), "{:?} speed {}", caste, speed);
+                    assert!(accel <= profile.max_acceleration * (1.0 + EPSILON), "{:?} accel {}", caste, accel);
+                    assert!(turn <= profile.turn_rate * dt + EPSILON, "{:?} turned {} rad", caste, turn);
+                    assert!(ant.bank.abs() <= profile.max_bank + EPSILON, "{:?} bank {}", caste, ant.bank);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fly_banks_into_a_sustained_turn() {
+        let profile = CasteProfile::default_for(Caste::Worker);
+        let mut ant = Ant::new(0, Caste::Worker, glm::Vec3::zeros(), glm::vec3(0.0, 0.0, 0.5), substream(7, 1));
+        for _ in 0..120 {
+            // Always pull hard to the ant's right.
+            let right = glm::cross(&ant.forward(), &ant.up());
+            fly(&mut ant, &profile, right * profile.max_acceleration * 10.0, 1.0 / 60.0);
+        }
+        assert!(ant.bank.abs() > 0.5 * profile.max_bank, "bank {}", ant.bank);
+        assert!(ant.bank.abs() <= profile.max_bank);
+    }
+}
@@ -0,0 +1,187 @@
+use nalgebra_glm as glm;
+
+use super::ant::Ant;
+use super::caste::CasteProfile;
+use super::pheromone::{Channel, PheromoneField};
+
+/// Pheromone laid per second while searching, so returning ants can find home.
+const HOME_DEPOSIT_RATE: f32 = 0.3;
+/// Pheromone laid per second while carrying food back to the nest.
+const TRAIL_DEPOSIT_RATE: f32 = 1.0;
+/// Pheromone laid per second at the nest after a successful trip.
+const RECRUIT_DEPOSIT_RATE: f32 = 2.0;
+const RECRUIT_TIME: f32 = 3.0;
+/// Ants inside their nest below this energy eat from its stock.
+const HUNGRY_ENERGY: f32 = 0.5;
+/// Time constant, in seconds, of the smoothed income in `ForagingStats`.
+const INCOME_WINDOW: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForageState {
+    /// Looking for food, laying home pheromone and following trails.
+    Searching,
+    /// Carrying food to the nest, laying trail pheromone.
+    Returning,
+    /// Just delivered food; marks the nest with recruitment pheromone.
+    Recruiting { remaining: f32 },
+}
+
+impl ForageState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForageState::Searching => "searching",
+            ForageState::Returning => "returning",
+            ForageState::Recruiting { .. } => "recruiting",
+        }
+    }
+
+    /// Whether an ant in this state climbs the gradient of `channel`.
+    pub fn attracted_to(&self, channel: Channel) -> bool {
+        matches!(
+            (self, channel),
+            (_, Channel::Alarm)
+                | (ForageState::Searching, Channel::Trail | Channel::Recruitment)
+                | (ForageState::Returning, Channel::Home)
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Nest {
+    pub id: u32,
+    pub position: glm::Vec3,
+    pub radius: f32,
+    /// Food stored in the nest.
+    pub stock: f32,
+    /// Food delivered over the whole run.
+    pub food_collected: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct FoodSource {
+    pub id: u32,
+    pub position: glm::Vec3,
+    pub radius: f32,
+    pub stock: f32,
+    pub capacity: f32,
+    /// Food regrown per second, up to `capacity`.
+    pub regeneration_rate: f32,
+}
+
+impl FoodSource {
+    pub fn regenerate(&mut self, dt: f32) {
+        self.stock = (self.stock + self.regeneration_rate * dt).min(self.capacity);
+    }
+}
+
+/// Colony-wide foraging metrics, used to compare parameter sets.
+#[derive(Clone, Debug, Default)]
+pub struct ForagingStats {
+    /// Food delivered to any nest over the whole run.
+    pub delivered: f32,
+    /// Completed round trips.
+    pub trips: u32,
+    /// Exponentially smoothed food income, in food per second.
+    pub recent_income: f32,
+    delivered_this_tick: f32,
+}
+
+impl ForagingStats {
+    /// Mean food income over `elapsed` seconds.
+    pub fn mean_income(&self, elapsed: f64) -> f32 {
+        if elapsed > 0.0 { (self.delivered as f64 / elapsed) as f32 } else { 0.0 }
+    }
+
+    pub fn end_tick(&mut self, dt: f32) {
+        let income = self.delivered_this_tick / dt;
+        self.recent_income += (income - self.recent_income) * (dt / INCOME_WINDOW).min(1.0);
+        self.delivered_this_tick = 0.0;
+    }
+}
+
+/// Advances one ant's foraging state machine by `dt`: picks food up, delivers
+/// it, lays pheromone and sets `Ant::target` for the `Seek` behavior.
+pub fn update(
+    ant: &mut Ant,
+    profile: &CasteProfile,
+    nests: &mut [Nest],
+    food_sources: &mut [FoodSource],
+    pheromones: &mut PheromoneField,
+    stats: &mut ForagingStats,
+    dt: f32,
+) {
+    let Some(nest) = ant.nest.and_then(|id| nests.iter_mut().find(|nest| nest.id == id)) else {
+        return;
+    };
+    let at_nest = glm::distance(&ant.position, &nest.position) < nest.radius;
+
+    if at_nest && ant.energy < HUNGRY_ENERGY {
+        let eaten = (1.0 - ant.energy).min(nest.stock);
+        nest.stock -= eaten;
+        ant.energy += eaten;
+    }
+
+    // Castes that cannot carry anything stay around the nest.
+    if profile.carrying_capacity <= 0.0 {
+        ant.target = Some(nest.position);
+        return;
+    }
+
+    match ant.state {
+        ForageState::Searching => {
+            pheromones.deposit(Channel::Home, &ant.position, HOME_DEPOSIT_RATE * dt);
+
+            let sensed = food_sources
+                .iter_mut()
+                .filter(|food| food.stock > 0.0)
+                .map(|food| (glm::distance(&ant.position, &food.position), food))
+                .filter(|(dist, food)| *dist < profile.sensing_radius + food.radius)
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            match sensed {
+                Some((dist, food)) if dist < food.radius => {
+                    let taken = profile.carrying_capacity.min(food.stock);
+                    food.stock -= taken;
+                    ant.carrying = taken;
+                    ant.food_memory = Some(food.position);
+                    ant.state = ForageState::Returning;
+                    ant.target = Some(nest.position);
+                },
+                Some((_, food)) => ant.target = Some(food.position),
+                None => {
+                    // Head back to the last known source; forget it once there and empty.
+                    if ant.food_memory.is_some_and(|memory| glm::distance(&ant.position, &memory) < profile.sensing_radius) {
+                        ant.food_memory = None;
+                    }
+                    ant.target = ant.food_memory;
+                },
+            }
+        },
+        ForageState::Returning => {
+            pheromones.deposit(Channel::Trail, &ant.position, TRAIL_DEPOSIT_RATE * dt);
+            ant.target = Some(nest.position);
+
+            if at_nest {
+                nest.stock += ant.carrying;
+                nest.food_collected += ant.carrying;
+                stats.delivered += ant.carrying;
+                stats.delivered_this_tick += ant.carrying;
+                stats.trips += 1;
+                ant.carrying = 0.0;
+                ant.state = ForageState::Recruiting { remaining: RECRUIT_TIME };
+            }
+        },
+        ForageState::Recruiting { remaining } => {
+            pheromones.deposit(Channel::Recruitment, &ant.position, RECRUIT_DEPOSIT_RATE * dt);
+            ant.target = Some(nest.position);
+
+            let remaining = remaining - dt;
+            ant.state = if remaining > 0.0 {
+                ForageState::Recruiting { remaining }
+            } else {
+                ant.target = ant.food_memory;
+                ForageState::Searching
+            };
+        },
+    }
+}
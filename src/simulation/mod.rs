@@ -0,0 +1,15 @@
+pub mod ant;
+pub mod caste;
+pub mod world;
+pub mod pheromone;
+pub mod timestep;
+pub mod steering;
+pub mod flight;
+pub mod foraging;
+pub mod spatial;
+pub mod rng;
+pub mod scenario;
+pub mod snapshot;
+pub mod replay;
+pub mod stats;
+pub mod export;
@@ -0,0 +1,395 @@
+use nalgebra_glm as glm;
+use serde::Deserialize;
+
+use super::spatial::CellMap;
+
+pub const CHUNK_SIZE: i32 = 8;
+pub const CHUNK_VOXELS: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+const FACE_OFFSETS: [glm::IVec3; 6] = [
+    glm::IVec3::new(1, 0, 0),
+    glm::IVec3::new(-1, 0, 0),
+    glm::IVec3::new(0, 1, 0),
+    glm::IVec3::new(0, -1, 0),
+    glm::IVec3::new(0, 0, 1),
+    glm::IVec3::new(0, 0, -1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Trail,
+    Alarm,
+    Recruitment,
+    Home,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] = [Channel::Trail, Channel::Alarm, Channel::Recruitment, Channel::Home];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Trail => "trail",
+            Channel::Alarm => "alarm",
+            Channel::Recruitment => "recruitment",
+            Channel::Home => "home",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelParams {
+    /// Diffusion coefficient in voxels² per second.
+    pub diffusion: f32,
+    /// Exponential decay rate per second (`value *= exp(-evaporation * dt)`).
+    pub evaporation: f32,
+    /// Chunks whose every voxel falls below this are freed.
+    pub min_value: f32,
+}
+
+impl ChannelParams {
+    pub fn default_for(channel: Channel) -> Self {
+        match channel {
+            Channel::Trail => ChannelParams { diffusion: 0.05, evaporation: 0.02, min_value: 1e-4 },
+            Channel::Alarm => ChannelParams { diffusion: 1.0, evaporation: 0.5, min_value: 1e-4 },
+            Channel::Recruitment => ChannelParams { diffusion: 0.3, evaporation: 0.1, min_value: 1e-4 },
+            Channel::Home => ChannelParams { diffusion: 0.02, evaporation: 0.005, min_value: 1e-4 },
+        }
+    }
+}
+
+/// One channel of the field, stored as a sparse set of `CHUNK_SIZE³` voxel chunks.
+#[derive(Clone, Default)]
+pub struct ChannelGrid {
+    chunks: CellMap<Box<[f32; CHUNK_VOXELS]>>,
+}
+
+impl ChannelGrid {
+    pub fn get(&self, voxel: glm::IVec3) -> f32 {
+        let (key, index) = split_voxel(voxel);
+        self.chunks.get(&key).map_or(0.0, |chunk| chunk[index])
+    }
+
+    fn add(&mut self, voxel: glm::IVec3, amount: f32) {
+        let (key, index) = split_voxel(voxel);
+        let chunk = self.chunks.entry(key).or_insert_with(|| Box::new([0.0; CHUNK_VOXELS]));
+        chunk[index] += amount;
+    }
+
+    /// Iterates the allocated chunks as `(chunk coordinate, voxels)`. Voxel `i` of
+    /// chunk `c` has grid coordinate `c * CHUNK_SIZE + chunk_local_coord(i)`.
+    pub fn chunks(&self) -> impl Iterator<Item = (glm::IVec3, &[f32])> {
+        self.chunks.iter().map(|(key, chunk)| (*key, &chunk[..]))
+    }
+
+    /// Voxels of the chunk at `key`, laid out as in `chunks`, if it is allocated.
+    pub fn chunk(&self, key: glm::IVec3) -> Option<&[f32]> {
+        self.chunks.get(&key).map(|chunk| &chunk[..])
+    }
+
+    /// Highest concentration anywhere in the channel.
+    pub fn peak(&self) -> f32 {
+        self.chunks.values().flat_map(|chunk| chunk.iter()).fold(0.0, |peak: f32, value| peak.max(*value))
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn total_mass(&self) -> f32 {
+        self.chunks.values().flat_map(|chunk| chunk.iter()).fold(0.0, |sum, value| sum + value)
+    }
+
+    fn diffuse(&mut self, rate: f32, dt: f32) {
+        // Explicit scheme; stable while rate * dt <= 1/6.
+        let k = (rate * dt).min(1.0 / 6.0);
+        if k <= 0.0 || self.chunks.is_empty() {
+            return;
+        }
+
+        let mut keys: CellMap<()> = CellMap::default();
+        for key in self.chunks.keys() {
+            keys.insert(*key, ());
+            for offset in FACE_OFFSETS {
+                keys.insert(key + offset, ());
+            }
+        }
+
+        let mut next = CellMap::default();
+        next.reserve(keys.len());
+        for key in keys.into_keys() {
+            let center = self.chunks.get(&key);
+            let neighbours = FACE_OFFSETS.map(|offset| self.chunks.get(&(key + offset)));
+            // `local` may step at most one voxel outside the chunk, through one face.
+            let value_at = |local: glm::IVec3| -> f32 {
+                let chunk = if local.iter().all(|c| (0..CHUNK_SIZE).contains(c)) {
+                    center
+                } else {
+                    let face = FACE_OFFSETS
+                        .iter()
+                        .position(|offset| {
+                            (0..3).any(|a| (offset[a] > 0 && local[a] >= CHUNK_SIZE) || (offset[a] < 0 && local[a] < 0))
+                        })
+                        .unwrap();
+                    neighbours[face]
+                };
+                chunk.map_or(0.0, |chunk| chunk[local_index(local.map(|c| c.rem_euclid(CHUNK_SIZE)))])
+            };
+
+            let mut out = Box::new([0.0; CHUNK_VOXELS]);
+            let mut any = false;
+            for (index, value) in out.iter_mut().enumerate() {
+                let local = chunk_local_coord(index);
+                let c = value_at(local);
+                let sum: f32 = FACE_OFFSETS.iter().map(|offset| value_at(local + offset)).sum();
+                *value = c + k * (sum - 6.0 * c);
+                any |= *value != 0.0;
+            }
+            if any {
+                next.insert(key, out);
+            }
+        }
+        self.chunks = next;
+    }
+
+    fn evaporate(&mut self, rate: f32, dt: f32, min_value: f32) {
+        let factor = (-rate * dt).exp();
+        self.chunks.retain(|_, chunk| {
+            let mut max: f32 = 0.0;
+            for value in chunk.iter_mut() {
+                *value *= factor;
+                max = max.max(*value);
+            }
+            max >= min_value
+        });
+    }
+}
+
+/// Volumetric pheromone concentrations for every `Channel`. Voxel `v` covers the
+/// cube `[v, v + 1) * voxel_size` in world space; values are sampled at voxel centres.
+pub struct PheromoneField {
+    voxel_size: f32,
+    grids: [ChannelGrid; Channel::ALL.len()],
+    pub params: [ChannelParams; Channel::ALL.len()],
+}
+
+impl PheromoneField {
+    pub fn new(voxel_size: f32) -> Self {
+        PheromoneField {
+            voxel_size,
+            grids: Default::default(),
+            params: Channel::ALL.map(ChannelParams::default_for),
+        }
+    }
+
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    /// Read-only access to a channel, e.g. for visualisation.
+    pub fn channel(&self, channel: Channel) -> &ChannelGrid {
+        &self.grids[channel.index()]
+    }
+
+    pub fn voxel_center(&self, voxel: glm::IVec3) -> glm::Vec3 {
+        (glm::convert::<glm::IVec3, glm::Vec3>(voxel) + glm::vec3(0.5, 0.5, 0.5)) * self.voxel_size
+    }
+
+    /// Adds `amount` at `position`, spread over the eight surrounding voxels with
+    /// trilinear weights so that `sample` varies smoothly.
+    pub fn deposit(&mut self, channel: Channel, position: &glm::Vec3, amount: f32) {
+        let (base, frac) = self.cell_coords(position);
+        let grid = &mut self.grids[channel.index()];
+        for (offset, weight) in trilinear_corners(&frac) {
+            if weight > 0.0 {
+                grid.add(base + offset, amount * weight);
+            }
+        }
+    }
+
+    pub fn sample(&self, channel: Channel, position: &glm::Vec3) -> f32 {
+        let (base, frac) = self.cell_coords(position);
+        let grid = &self.grids[channel.index()];
+        trilinear_corners(&frac).iter().map(|(offset, weight)| grid.get(base + offset) * weight).sum()
+    }
+
+    /// Gradient of the trilinearly interpolated concentration, in units per world unit.
+    pub fn gradient(&self, channel: Channel, position: &glm::Vec3) -> glm::Vec3 {
+        let (base, frac) = self.cell_coords(position);
+        let grid = &self.grids[channel.index()];
+        let mut gradient = glm::Vec3::zeros();
+        for (offset, _) in trilinear_corners(&frac) {
+            let value = grid.get(base + offset);
+            if value == 0.0 {
+                continue;
+            }
+            // d/dx of the corner weight is +-(wy * wz), and likewise for y and z.
+            let weight = |a: usize| if offset[a] == 1 { frac[a] } else { 1.0 - frac[a] };
+            let sign = |a: usize| if offset[a] == 1 { 1.0 } else { -1.0 };
+            gradient += value * glm::vec3(
+                sign(0) * weight(1) * weight(2),
+                sign(1) * weight(0) * weight(2),
+                sign(2) * weight(0) * weight(1),
+            );
+        }
+        gradient / self.voxel_size
+    }
+
+    pub fn total_mass(&self, channel: Channel) -> f32 {
+        self.grids[channel.index()].total_mass()
+    }
+
+    /// Puts back a chunk read from a snapshot.
+    pub(super) fn restore_chunk(&mut self, channel: Channel, key: glm::IVec3, values: Box<[f32; CHUNK_VOXELS]>) {
+        self.grids[channel.index()].chunks.insert(key, values);
+    }
+
+    /// Diffuses and evaporates every channel by one tick of length `dt`.
+    pub fn update(&mut self, dt: f32) {
+        for channel in Channel::ALL {
+            let params = self.params[channel.index()];
+            let grid = &mut self.grids[channel.index()];
+            grid.diffuse(params.diffusion, dt);
+            grid.evaporate(params.evaporation, dt, params.min_value);
+        }
+    }
+
+    fn cell_coords(&self, position: &glm::Vec3) -> (glm::IVec3, glm::Vec3) {
+        let g = position / self.voxel_size - glm::vec3(0.5, 0.5, 0.5);
+        let floor = glm::floor(&g);
+        (glm::convert_unchecked::<glm::Vec3, glm::IVec3>(floor), g - floor)
+    }
+}
+
+pub fn chunk_local_coord(index: usize) -> glm::IVec3 {
+    let index = index as i32;
+    glm::vec3(index % CHUNK_SIZE, (index / CHUNK_SIZE) % CHUNK_SIZE, index / (CHUNK_SIZE * CHUNK_SIZE))
+}
+
+fn split_voxel(voxel: glm::IVec3) -> (glm::IVec3, usize) {
+    let key = voxel.map(|c| c.div_euclid(CHUNK_SIZE));
+    let local = voxel.map(|c| c.rem_euclid(CHUNK_SIZE));
+    (key, local_index(local))
+}
+
+/// Index into a chunk of the voxel at chunk coordinate `local`; the inverse of `chunk_local_coord`.
+pub fn local_index(local: glm::IVec3) -> usize {
+    (local.x + CHUNK_SIZE * (local.y + CHUNK_SIZE * local.z)) as usize
+}
+
+fn trilinear_corners(frac: &glm::Vec3) -> [(glm::IVec3, f32); 8] {
+    let mut corners = [(glm::IVec3::zeros(), 0.0); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let offset = glm::vec3((i & 1) as i32, ((i >> 1) & 1) as i32, ((i >> 2) & 1) as i32);
+        let wx = if offset.x == 1 { frac.x } else { 1.0 - frac.x };
+        let wy = if offset.y == 1 { frac.y } else { 1.0 - frac.y };
+        let wz = if offset.z == 1 { frac.z } else { 1.0 - frac.z };
+        *corner = (offset, wx * wy * wz);
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOXEL: f32 = 0.5;
+
+    /// A field whose `channel` diffuses, evaporates and frees chunks exactly as given.
+    fn field(channel: Channel, diffusion: f32, evaporation: f32, min_value: f32) -> PheromoneField {
+        let mut field = PheromoneField::new(VOXEL);
+        field.params[channel.index()] = ChannelParams { diffusion, evaporation, min_value };
+        field
+    }
+
+    #[test]
+    fn deposit_at_voxel_centre_samples_back_exactly() {
+        let mut field = field(Channel::Trail, 0.0, 0.0, 0.0);
+        for voxel in [glm::vec3(0, 0, 0), glm::vec3(-1, 3, 8), glm::vec3(7, -9, -16)] {
+            let center = field.voxel_center(voxel);
+            field.deposit(Channel::Trail, &center, 2.5);
+            assert_eq!(field.channel(Channel::Trail).get(voxel), 2.5);
+            assert!((field.sample(Channel::Trail, &center) - 2.5).abs() < 1e-6, "voxel {}", voxel);
+        }
+        assert_eq!(field.sample(Channel::Alarm, &glm::Vec3::zeros()), 0.0);
+    }
+
+    #[test]
+    fn diffusion_conserves_mass() {
+        let mut field = field(Channel::Alarm, 1.0, 0.0, 0.0);
+        // Straddle a chunk corner so mass has to flow between chunks.
+        for position in [glm::vec3(0.1, 0.2, -0.3), glm::vec3(-2.0, 1.7, 0.4), glm::vec3(3.9, 3.9, 3.9)] {
+            field.deposit(Channel::Alarm, &position, 10.0);
+        }
+        let before = field.total_mass(Channel::Alarm);
+        // Every tick spreads into the face-adjacent chunks, so keep the run short.
+        for _ in 0..5 {
+            field.update(0.1);
+        }
+        let after = field.total_mass(Channel::Alarm);
+        assert!((after - before).abs() < before * 1e-4, "mass {} -> {}", before, after);
+        assert!(field.channel(Channel::Alarm).chunk_count() > 3);
+    }
+
+    #[test]
+    fn evaporation_decays_exponentially() {
+        let (rate, dt) = (0.4, 0.5);
+        let mut field = field(Channel::Home, 0.0, rate, 0.0);
+        let voxels = [glm::vec3(0, 0, 0), glm::vec3(5, -3, 12), glm::vec3(-8, 0, 1)];
+        for (i, voxel) in voxels.iter().enumerate() {
+            field.deposit(Channel::Home, &field.voxel_center(*voxel), 1.0 + i as f32);
+        }
+        let before: Vec<f32> = voxels.iter().map(|v| field.channel(Channel::Home).get(*v)).collect();
+        field.update(dt);
+        for (voxel, before) in voxels.iter().zip(before) {
+            let expected = before * (-rate * dt).exp();
+            let value = field.channel(Channel::Home).get(*voxel);
+            assert!((value - expected).abs() < 1e-6, "voxel {}: {} != {}", voxel, value, expected);
+        }
+    }
+
+    #[test]
+    fn gradient_points_towards_a_deposit() {
+        let mut field = field(Channel::Recruitment, 1.0, 0.0, 0.0);
+        let source = glm::vec3(1.0, -0.5, 2.0);
+        field.deposit(Channel::Recruitment, &source, 5.0);
+        for _ in 0..10 {
+            field.update(0.1);
+        }
+        for direction in [
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(0.0, -1.0, 0.0),
+            glm::vec3(0.0, 0.0, 1.0),
+            glm::vec3(-1.0, 1.0, 1.0),
+            glm::vec3(0.3, -0.7, -0.6),
+        ] {
+            let position = source + glm::normalize(&direction) * 3.0 * VOXEL;
+            let gradient = field.gradient(Channel::Recruitment, &position);
+            let towards = glm::normalize(&(source - position));
+            assert!(glm::dot(&gradient, &towards) > 0.5 * glm::length(&gradient), "at {}: {}", position, gradient);
+        }
+    }
+
+    #[test]
+    fn chunks_are_freed_below_min_value() {
+        let mut field = field(Channel::Trail, 0.0, 1.0, 0.1);
+        field.deposit(Channel::Trail, &field.voxel_center(glm::vec3(0, 0, 0)), 1.0);
+        field.deposit(Channel::Trail, &field.voxel_center(glm::vec3(20, 0, 0)), 0.12);
+        assert_eq!(field.channel(Channel::Trail).chunk_count(), 2);
+
+        // 0.12 * e^-1 is below the floor but 1.0 * e^-1 is not.
+        field.update(1.0);
+        assert_eq!(field.channel(Channel::Trail).chunk_count(), 1);
+        assert_eq!(field.channel(Channel::Trail).get(glm::vec3(20, 0, 0)), 0.0);
+
+        for _ in 0..2 {
+            field.update(1.0);
+        }
+        assert_eq!(field.channel(Channel::Trail).chunk_count(), 0);
+        assert_eq!(field.total_mass(Channel::Trail), 0.0);
+    }
+}
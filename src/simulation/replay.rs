@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use nalgebra_glm as glm;
+
+use super::caste::{Caste, CasteProfile};
+use super::pheromone::{Channel, ChannelParams};
+use super::snapshot::{self, Reader, Writer};
+use super::world::{World, FIXED_DT};
+
+const MAGIC: &[u8; 8] = b"ANTREPLY";
+/// Version of the replay container. The embedded initial snapshot carries its
+/// own version and is migrated by `snapshot::load`.
+pub const REPLAY_VERSION: u32 = 1;
+/// Ticks between the snapshots a `Timeline` keeps for seeking.
+pub const KEYFRAME_INTERVAL: u64 = 600;
+
+/// Something from outside the simulation that changed the world. Together with
+/// the initial snapshot these are all a run needs to be reproduced.
+#[derive(Clone, Debug)]
+pub enum Input {
+    RespawnFood { count: u32 },
+    AddFood { position: glm::Vec3, radius: f32, capacity: f32, regeneration_rate: f32 },
+    SetChannelParams { channel: Channel, params: ChannelParams },
+    SetCasteProfile { caste: Caste, profile: CasteProfile },
+}
+
+impl Input {
+    pub fn apply(&self, world: &mut World) {
+        match self {
+            Input::RespawnFood { count } => world.respawn_food_sources(*count as usize),
+            Input::AddFood { position, radius, capacity, regeneration_rate } => {
+                world.add_food_source(*position, *radius, *capacity, *regeneration_rate);
+            },
+            Input::SetChannelParams { channel, params } => world.pheromones.params[channel.index()] = *params,
+            Input::SetCasteProfile { caste, profile } => {
+                let mut castes = world.castes().clone();
+                *castes.get_mut(*caste) = *profile;
+                world.set_castes(castes);
+            },
+        }
+    }
+
+    fn write(&self, out: &mut Writer) {
+        match self {
+            Input::RespawnFood { count } => {
+                out.u8(0);
+                out.u32(*count);
+            },
+            Input::AddFood { position, radius, capacity, regeneration_rate } => {
+                out.u8(1);
+                out.vec3(position);
+                out.f32s(&[*radius, *capacity, *regeneration_rate]);
+            },
+            Input::SetChannelParams { channel, params } => {
+                out.u8(2);
+                out.u8(channel.index() as u8);
+                out.f32s(&[params.diffusion, params.evaporation, params.min_value]);
+            },
+            Input::SetCasteProfile { caste, profile } => {
+                out.u8(3);
+                out.u8(caste.index() as u8);
+                snapshot::write_profile(out, profile);
+            },
+        }
+    }
+
+    fn read(input: &mut Reader) -> Result<Self, String> {
+        Ok(match input.u8()? {
+            0 => Input::RespawnFood { count: input.u32()? },
+            1 => Input::AddFood {
+                position: input.vec3()?,
+                radius: input.f32()?,
+                capacity: input.f32()?,
+                regeneration_rate: input.f32()?,
+            },
+            2 => Input::SetChannelParams {
+                channel: *Channel::ALL.get(input.u8()? as usize).ok_or("Invalid pheromone channel")?,
+                params: ChannelParams { diffusion: input.f32()?, evaporation: input.f32()?, min_value: input.f32()? },
+            },
+            3 => Input::SetCasteProfile {
+                caste: *Caste::ALL.get(input.u8()? as usize).ok_or("Invalid caste")?,
+                profile: snapshot::read_profile(input)?,
+            },
+            other => return Err(format!("Invalid input kind {}", other)),
+        })
+    }
+}
+
+/// A run stored as its initial snapshot plus the inputs applied on the way,
+/// in order and keyed by the tick they were applied at.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    initial: Vec<u8>,
+    start_tick: u64,
+    inputs: Vec<(u64, Input)>,
+    /// Last tick the recorded run reached.
+    end_tick: u64,
+}
+
+impl Recording {
+    pub fn new(world: &World) -> Self {
+        Recording { initial: snapshot::save(world), start_tick: world.tick, inputs: Vec::new(), end_tick: world.tick }
+    }
+
+    pub fn start_tick(&self) -> u64 {
+        self.start_tick
+    }
+
+    pub fn end_tick(&self) -> u64 {
+        self.end_tick
+    }
+
+    pub fn inputs(&self) -> &[(u64, Input)] {
+        &self.inputs
+    }
+
+    pub fn save(&self) -> Vec<u8> {
+        let mut out = Writer(Vec::new());
+        out.bytes(MAGIC);
+        out.u32(REPLAY_VERSION);
+        out.u64(self.start_tick);
+        out.u64(self.end_tick);
+        out.u32(self.initial.len() as u32);
+        out.bytes(&self.initial);
+        out.u32(self.inputs.len() as u32);
+        for (tick, input) in &self.inputs {
+            out.u64(*tick);
+            input.write(&mut out);
+        }
+        out.0
+    }
+
+    pub fn load(bytes: &[u8]) -> Result<Self, String> {
+        let mut input = Reader { bytes, at: 0 };
+        if input.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err("Not a replay".to_string());
+        }
+        let version = input.u32()?;
+        if version != REPLAY_VERSION {
+            return Err(format!("Unsupported replay version {} (this build reads version {})", version, REPLAY_VERSION));
+        }
+        let start_tick = input.u64()?;
+        let end_tick = input.u64()?;
+        let len = input.count()? as usize;
+        let initial = input.take(len)?.to_vec();
+        let mut inputs = Vec::new();
+        for _ in 0..input.count()? {
+            let tick = input.u64()?;
+            if tick < inputs.last().map_or(start_tick, |(last, _)| *last) || tick > end_tick {
+                return Err(format!("Input at tick {} is out of order", tick));
+            }
+            inputs.push((tick, Input::read(&mut input)?));
+        }
+        if input.at != bytes.len() {
+            return Err(format!("{} unexpected bytes after the end of the replay", bytes.len() - input.at));
+        }
+        Ok(Recording { initial, start_tick, inputs, end_tick })
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.save()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::load(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// A world together with its recorded history. Stepping past the end of the
+/// recording extends it; stepping inside it re-applies the recorded inputs, so
+/// any tick can be revisited exactly. Inputs made in the past start a new
+/// branch and drop the recorded future.
+pub struct Timeline {
+    world: World,
+    recording: Recording,
+    /// Snapshots taken every `keyframe_interval` ticks, before that tick's inputs.
+    keyframes: BTreeMap<u64, Vec<u8>>,
+    /// 0 keeps no keyframes; seeking then replays from the start.
+    keyframe_interval: u64,
+}
+
+impl Timeline {
+    /// Starts recording `world` from its current state.
+    pub fn new(world: World) -> Self {
+        Timeline { recording: Recording::new(&world), world, keyframes: BTreeMap::new(), keyframe_interval: KEYFRAME_INTERVAL }
+    }
+
+    /// Opens a recording for playback, positioned at its start.
+    pub fn from_recording(recording: Recording) -> Result<Self, String> {
+        let mut world = snapshot::load(&recording.initial)?;
+        if world.tick != recording.start_tick {
+            return Err(format!(
+                "Replay starts at tick {} but its snapshot is at tick {}",
+                recording.start_tick, world.tick
+            ));
+        }
+        apply_inputs(&recording.inputs, &mut world);
+        Ok(Timeline { world, recording, keyframes: BTreeMap::new(), keyframe_interval: KEYFRAME_INTERVAL })
+    }
+
+    pub fn set_keyframe_interval(&mut self, interval: u64) {
+        self.keyframe_interval = interval;
+        self.keyframes.clear();
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.world.tick
+    }
+
+    /// Whether the world is behind the end of the recording, i.e. replaying.
+    pub fn is_replaying(&self) -> bool {
+        self.world.tick < self.recording.end_tick
+    }
+
+    /// Advances one fixed tick, then applies whatever was recorded for the new tick.
+    pub fn step(&mut self) {
+        self.world.step(FIXED_DT);
+        let tick = self.world.tick;
+        if self.keyframe_interval > 0 && tick.is_multiple_of(self.keyframe_interval) && !self.keyframes.contains_key(&tick) {
+            self.keyframes.insert(tick, snapshot::save(&self.world));
+        }
+        apply_inputs(&self.recording.inputs, &mut self.world);
+        self.recording.end_tick = self.recording.end_tick.max(tick);
+    }
+
+    /// Applies `input` now and records it at the current tick.
+    pub fn apply(&mut self, input: Input) {
+        let tick = self.world.tick;
+        if self.is_replaying() {
+            self.recording.inputs.retain(|(at, _)| *at <= tick);
+            self.keyframes.retain(|at, _| *at <= tick);
+            self.recording.end_tick = tick;
+        }
+        input.apply(&mut self.world);
+        self.recording.inputs.push((tick, input));
+    }
+
+    /// Moves to `tick`, clamped to the recorded range, restoring the closest
+    /// earlier keyframe and replaying from there.
+    pub fn seek(&mut self, tick: u64) -> Result<(), String> {
+        let tick = tick.clamp(self.recording.start_tick, self.recording.end_tick);
+        if tick < self.world.tick || self.keyframes.range(self.world.tick + 1..=tick).next_back().is_some() {
+            let (at, bytes) = self
+                .keyframes
+                .range(..=tick)
+                .next_back()
+                .map_or((self.recording.start_tick, &self.recording.initial), |(at, bytes)| (*at, bytes));
+            self.world = snapshot::load(bytes)?;
+            debug_assert_eq!(self.world.tick, at);
+            apply_inputs(&self.recording.inputs, &mut self.world);
+        }
+        while self.world.tick < tick {
+            self.step();
+        }
+        Ok(())
+    }
+}
+
+/// Applies the inputs recorded at the world's current tick.
+fn apply_inputs(inputs: &[(u64, Input)], world: &mut World) {
+    let tick = world.tick;
+    let start = inputs.partition_point(|(at, _)| *at < tick);
+    for (_, input) in inputs[start..].iter().take_while(|(at, _)| *at == tick) {
+        input.apply(world);
+    }
+}
@@ -0,0 +1,48 @@
+use nalgebra_glm as glm;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Generator behind every random decision of the simulation. A run is fully
+/// determined by its seed and the inputs fed into it.
+pub type SimRng = ChaCha8Rng;
+
+/// Stream of the world itself: scene setup, food placement and other
+/// decisions not tied to a single ant.
+pub const WORLD_STREAM: u64 = 0;
+
+/// Independent generator number `stream` of `seed`.
+pub fn substream(seed: u64, stream: u64) -> SimRng {
+    let mut rng = SimRng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng
+}
+
+/// Generator of the ant with id `ant_id`. Each ant owns its stream, so what it
+/// draws does not depend on how many numbers the ants stepped before it drew.
+pub fn ant_stream(seed: u64, ant_id: u32) -> SimRng {
+    substream(seed, WORLD_STREAM + 1 + ant_id as u64)
+}
+
+/// Fresh seed for runs started without `--seed`.
+pub fn random_seed() -> u64 {
+    rand::rng().random()
+}
+
+pub fn random_on_sphere<R: Rng + ?Sized>(rng: &mut R, radius: f32) -> glm::Vec3 {
+    let u: f32 = rng.random_range(0.0..1.0);
+    let v: f32 = rng.random_range(0.0..1.0);
+
+    let theta = 2.0 * std::f32::consts::PI * u;
+    let phi = (1.0 - 2.0 * v).acos();
+
+    glm::vec3(
+        radius * phi.sin() * theta.cos(),
+        radius * phi.sin() * theta.sin(),
+        radius * phi.cos(),
+    )
+}
+
+pub fn random_in_sphere<R: Rng + ?Sized>(rng: &mut R, radius: f32) -> glm::Vec3 {
+    let r: f32 = rng.random_range(0.0f32..1.0).cbrt() * radius;
+    random_on_sphere(rng, r)
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use nalgebra_glm as glm;
+use serde::Deserialize;
+use toml::Spanned;
+
+use super::caste::{Caste, CasteTable};
+use super::pheromone::{Channel, ChannelParams};
+use super::world::{Obstacle, World, DEFAULT_FOOD_CAPACITY, DEFAULT_FOOD_RADIUS, DEFAULT_FOOD_REGENERATION};
+
+/// Source of `Scenario::default`, also shipped as an annotated example.
+pub const DEFAULT_SCENARIO: &str = include_str!("../../assets/scenarios/default.toml");
+
+/// Initial setup of a run: the world, its colonies, food, obstacles and
+/// pheromone parameters, the seed and how long it lasts.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// Seed of the run; a random one is picked when absent. `--seed` wins over it.
+    pub seed: Option<u64>,
+    /// Ticks a headless run lasts.
+    pub ticks: u64,
+    pub bounds_radius: f32,
+    pub colonies: Vec<ColonySpec>,
+    pub food_sources: Vec<FoodSpec>,
+    /// Standard food sources scattered in the outer half of the world.
+    pub random_food_sources: usize,
+    pub obstacles: Vec<Obstacle>,
+    pub pheromones: [ChannelParams; Channel::ALL.len()],
+}
+
+#[derive(Clone, Debug)]
+pub struct ColonySpec {
+    pub nest: glm::Vec3,
+    pub nest_radius: f32,
+    /// Ants of each caste spawned inside the nest, indexed by `Caste`.
+    pub castes: [usize; Caste::ALL.len()],
+}
+
+#[derive(Clone, Debug)]
+pub struct FoodSpec {
+    pub position: glm::Vec3,
+    pub radius: f32,
+    pub capacity: f32,
+    pub regeneration_rate: f32,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario::parse(DEFAULT_SCENARIO, "default scenario").expect("Built-in scenario is invalid")
+    }
+}
+
+impl Scenario {
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        Self::parse(source, "scenario")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&source, &path.display().to_string())
+    }
+
+    /// Parses and validates `source`. Every problem found is reported on its own
+    /// line as `origin:line:column: message`.
+    fn parse(source: &str, origin: &str) -> Result<Self, String> {
+        let mut validator = Validator { source, origin, errors: Vec::new() };
+        match toml::from_str::<ScenarioFile>(source) {
+            Ok(file) => {
+                let scenario = validator.scenario(file);
+                if validator.errors.is_empty() {
+                    return Ok(scenario);
+                }
+            },
+            Err(err) => validator.error(err.span().unwrap_or(0..0), err.message().trim_end()),
+        }
+        Err(validator.errors.join("\n"))
+    }
+
+    /// Builds the initial world. Everything random is drawn from `seed`.
+    pub fn build(&self, castes: CasteTable, seed: u64) -> World {
+        let mut world = World::new(self.bounds_radius, seed);
+        world.set_castes(castes);
+        world.pheromones.params = self.pheromones;
+        world.obstacles = self.obstacles.clone();
+        for colony in &self.colonies {
+            let nest = world.add_nest(colony.nest, colony.nest_radius);
+            for caste in Caste::ALL {
+                world.populate_nest(nest, caste, colony.castes[caste.index()]);
+            }
+        }
+        world.respawn_food_sources(self.random_food_sources);
+        for food in &self.food_sources {
+            world.add_food_source(food.position, food.radius, food.capacity, food.regeneration_rate);
+        }
+        world
+    }
+}
+
+// The file as written, with source positions kept for error messages.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    seed: Option<u64>,
+    ticks: Option<u64>,
+    bounds_radius: Option<Spanned<f32>>,
+    #[serde(default)]
+    colonies: Vec<ColonyFile>,
+    #[serde(default)]
+    food_sources: Vec<FoodFile>,
+    random_food_sources: Option<usize>,
+    #[serde(default)]
+    obstacles: Vec<ObstacleFile>,
+    #[serde(default)]
+    pheromones: HashMap<Channel, ChannelFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColonyFile {
+    nest: Spanned<[f32; 3]>,
+    nest_radius: Option<Spanned<f32>>,
+    #[serde(default)]
+    castes: HashMap<Caste, usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FoodFile {
+    position: Spanned<[f32; 3]>,
+    radius: Option<Spanned<f32>>,
+    capacity: Option<Spanned<f32>>,
+    regeneration_rate: Option<Spanned<f32>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObstacleFile {
+    center: Spanned<[f32; 3]>,
+    radius: Spanned<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChannelFile {
+    diffusion: Option<Spanned<f32>>,
+    evaporation: Option<Spanned<f32>>,
+    min_value: Option<Spanned<f32>>,
+}
+
+const DEFAULT_TICKS: u64 = 36_000;
+const DEFAULT_BOUNDS_RADIUS: f32 = 3.0;
+const DEFAULT_NEST_RADIUS: f32 = 0.4;
+
+/// Turns a `ScenarioFile` into a `Scenario`, collecting every problem with its
+/// position in the source.
+struct Validator<'a> {
+    source: &'a str,
+    origin: &'a str,
+    errors: Vec<String>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, span: Range<usize>, message: impl Display) {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        self.errors.push(format!("{}:{}:{}: {}", self.origin, line, column, message));
+    }
+
+    /// Value of an optional number, checked to be finite and accepted by `valid`.
+    fn number(&mut self, value: Option<Spanned<f32>>, default: f32, name: &str, rule: &str, valid: fn(f32) -> bool) -> f32 {
+        let Some(value) = value else {
+            return default;
+        };
+        let (span, value) = (value.span(), value.into_inner());
+        if !value.is_finite() || !valid(value) {
+            self.error(span, format!("{} must be {}, got {}", name, rule, value));
+        }
+        value
+    }
+
+    fn positive(&mut self, value: Option<Spanned<f32>>, default: f32, name: &str) -> f32 {
+        self.number(value, default, name, "positive", |v| v > 0.0)
+    }
+
+    fn non_negative(&mut self, value: Option<Spanned<f32>>, default: f32, name: &str) -> f32 {
+        self.number(value, default, name, "zero or positive", |v| v >= 0.0)
+    }
+
+    /// Point that must lie inside the world, `margin` away from its boundary.
+    fn point(&mut self, value: &Spanned<[f32; 3]>, bounds_radius: f32, margin: f32, name: &str) -> glm::Vec3 {
+        let point = glm::make_vec3(value.get_ref());
+        if point.iter().any(|c| !c.is_finite()) {
+            self.error(value.span(), format!("{} must be finite", name));
+        } else if glm::length(&point) + margin > bounds_radius {
+            self.error(
+                value.span(),
+                format!("{} lies outside the world (bounds_radius = {})", name, bounds_radius),
+            );
+        }
+        point
+    }
+
+    fn scenario(&mut self, file: ScenarioFile) -> Scenario {
+        let bounds_radius = self.positive(file.bounds_radius, DEFAULT_BOUNDS_RADIUS, "bounds_radius");
+
+        let obstacles: Vec<(Obstacle, Range<usize>)> = file
+            .obstacles
+            .into_iter()
+            .map(|obstacle| {
+                let radius = self.positive(Some(obstacle.radius), 0.0, "obstacle radius");
+                let center = self.point(&obstacle.center, bounds_radius, 0.0, "obstacle center");
+                (Obstacle { center, radius }, obstacle.center.span())
+            })
+            .collect();
+
+        let mut colonies = Vec::new();
+        for colony in file.colonies {
+            let nest_radius = self.positive(colony.nest_radius, DEFAULT_NEST_RADIUS, "nest_radius");
+            let nest = self.point(&colony.nest, bounds_radius, nest_radius, "nest");
+            self.check_clear_of(&obstacles, &nest, colony.nest.span(), "nest");
+            let mut castes = [0; Caste::ALL.len()];
+            for (caste, count) in colony.castes {
+                castes[caste.index()] = count;
+            }
+            colonies.push(ColonySpec { nest, nest_radius, castes });
+        }
+
+        let mut food_sources = Vec::new();
+        for food in file.food_sources {
+            let position = self.point(&food.position, bounds_radius, 0.0, "food source");
+            self.check_clear_of(&obstacles, &position, food.position.span(), "food source");
+            let radius = self.positive(food.radius, DEFAULT_FOOD_RADIUS, "food radius");
+            let capacity = self.positive(food.capacity, DEFAULT_FOOD_CAPACITY, "capacity");
+            let regeneration_rate =
+                self.non_negative(food.regeneration_rate, DEFAULT_FOOD_REGENERATION, "regeneration_rate");
+            food_sources.push(FoodSpec { position, radius, capacity, regeneration_rate });
+        }
+
+        let mut pheromones = Channel::ALL.map(ChannelParams::default_for);
+        // Visit channels in a fixed order so errors come out in a stable order.
+        let mut channels = file.pheromones;
+        for channel in Channel::ALL {
+            let Some(spec) = channels.remove(&channel) else {
+                continue;
+            };
+            let params = &mut pheromones[channel.index()];
+            params.diffusion = self.non_negative(spec.diffusion, params.diffusion, "diffusion");
+            params.evaporation = self.non_negative(spec.evaporation, params.evaporation, "evaporation");
+            params.min_value = self.positive(spec.min_value, params.min_value, "min_value");
+        }
+
+        Scenario {
+            seed: file.seed,
+            ticks: file.ticks.unwrap_or(DEFAULT_TICKS),
+            bounds_radius,
+            colonies,
+            food_sources,
+            random_food_sources: file.random_food_sources.unwrap_or(0),
+            obstacles: obstacles.into_iter().map(|(obstacle, _)| obstacle).collect(),
+            pheromones,
+        }
+    }
+
+    fn check_clear_of(&mut self, obstacles: &[(Obstacle, Range<usize>)], point: &glm::Vec3, span: Range<usize>, name: &str) {
+        for (obstacle, obstacle_span) in obstacles {
+            if glm::distance(point, &obstacle.center) < obstacle.radius {
+                let line = self.source[..obstacle_span.start].matches('\n').count() + 1;
+                self.error(span.clone(), format!("{} is inside the obstacle on line {}", name, line));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str) -> String {
+        Scenario::parse(source, "test.toml").expect_err("scenario should be rejected")
+    }
+
+    #[test]
+    fn default_scenario_is_valid() {
+        let scenario = Scenario::parse(DEFAULT_SCENARIO, "default.toml").unwrap();
+        assert!(scenario.bounds_radius > 0.0);
+        assert!(!scenario.colonies.is_empty());
+    }
+
+    #[test]
+    fn zero_bounds_radius_is_rejected() {
+        assert_eq!(errors("bounds_radius = 0.0\n"), "test.toml:1:17: bounds_radius must be positive, got 0");
+    }
+
+    #[test]
+    fn negative_food_capacity_is_rejected() {
+        let source = "[[food_sources]]\nposition = [1.0, 0.0, 0.0]\ncapacity = -5.0\n";
+        assert_eq!(errors(source), "test.toml:3:12: capacity must be positive, got -5");
+    }
+
+    #[test]
+    fn unknown_caste_is_rejected() {
+        let source = "[[colonies]]\nnest = [0.0, 0.0, 0.0]\ncastes = { worker = 10, drone = 3 }\n";
+        assert_eq!(
+            errors(source),
+            "test.toml:3:25: unknown variant `drone`, expected one of `worker`, `forager`, `soldier`, `queen`, `scout`"
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported_in_source_order() {
+        let source = "\
+bounds_radius = 2.0
+
+[[colonies]]
+nest = [1.8, 0.0, 0.0]
+nest_radius = 0.5
+
+[[obstacles]]
+center = [0.0, 1.0, 0.0]
+radius = 0.5
+
+[[food_sources]]
+position = [0.0, 1.2, 0.0]
+radius = 0.0
+
+[pheromones.alarm]
+min_value = 0.0
+";
+        assert_eq!(
+            errors(source),
+            "test.toml:4:8: nest lies outside the world (bounds_radius = 2)\n\
+             test.toml:12:12: food source is inside the obstacle on line 8\n\
+             test.toml:13:10: food radius must be positive, got 0\n\
+             test.toml:16:13: min_value must be positive, got 0"
+        );
+    }
+}
@@ -0,0 +1,460 @@
+use std::fs;
+use std::path::Path;
+
+use nalgebra_glm as glm;
+
+use super::ant::Ant;
+use super::caste::{Caste, CasteProfile, CasteTable, PheromoneSensitivity};
+use super::foraging::{FoodSource, ForageState, ForagingStats, Nest};
+use super::pheromone::{Channel, ChannelParams, PheromoneField, CHUNK_VOXELS};
+use super::rng::SimRng;
+use super::steering::SteeringWeights;
+use super::world::{Obstacle, World};
+
+/// First bytes of every snapshot file.
+const MAGIC: &[u8; 8] = b"ANTSNAP\0";
+/// Version written by `save`. Bump it whenever the layout below changes and
+/// add the upgrade from the previous version to `MIGRATIONS`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Serializes the complete state of `world`: ants with their random streams,
+/// pheromone grids, nests, food, obstacles, caste profiles and counters.
+/// Custom pipelines installed with `World::set_steering` are not saved; a
+/// restored world uses the pipelines built from its caste profiles.
+pub fn save(world: &World) -> Vec<u8> {
+    let mut out = Writer(Vec::new());
+    out.bytes(MAGIC);
+    out.u32(SNAPSHOT_VERSION);
+    write_world(&mut out, world);
+    out.0
+}
+
+/// Restores a world saved by `save`, upgrading older versions on the way.
+pub fn load(bytes: &[u8]) -> Result<World, String> {
+    if bytes.len() < MAGIC.len() + 4 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err("Not a simulation snapshot".to_string());
+    }
+    let mut version = u32::from_le_bytes(bytes[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return Err(format!(
+            "Unsupported snapshot version {} (this build reads up to version {})",
+            version, SNAPSHOT_VERSION
+        ));
+    }
+    let mut body = bytes[MAGIC.len() + 4..].to_vec();
+    while version < SNAPSHOT_VERSION {
+        body = MIGRATIONS[version as usize - 1](body).map_err(|e| format!("Upgrading version {}: {}", version, e))?;
+        version += 1;
+    }
+
+    let mut input = Reader { bytes: &body, at: 0 };
+    let world = read_world(&mut input)?;
+    if input.at != body.len() {
+        return Err(format!("{} unexpected bytes after the end of the snapshot", body.len() - input.at));
+    }
+    Ok(world)
+}
+
+pub fn save_to_file<P: AsRef<Path>>(world: &World, path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    fs::write(path, save(world)).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<World, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    load(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Rewrites a snapshot body from one version into the next.
+type Migration = fn(Vec<u8>) -> Result<Vec<u8>, String>;
+
+/// `MIGRATIONS[v - 1]` upgrades the body of a version `v` snapshot to version
+/// `v + 1`. Bumping `SNAPSHOT_VERSION` without adding an entry does not compile.
+const MIGRATIONS: [Migration; SNAPSHOT_VERSION as usize - 1] = [];
+
+// Layout of version 1. Everything is little endian; counts are u32.
+
+fn write_world(out: &mut Writer, world: &World) {
+    out.u64(world.seed);
+    out.u64(world.tick);
+    out.f64(world.time);
+    out.f32(world.bounds_radius);
+    out.u32(world.next_ant_id);
+    out.u32(world.next_entity_id);
+    write_rng(out, &world.rng);
+
+    for caste in Caste::ALL {
+        write_profile(out, world.castes().get(caste));
+    }
+
+    out.f32(world.foraging.delivered);
+    out.u32(world.foraging.trips);
+    out.f32(world.foraging.recent_income);
+
+    out.u32(world.obstacles.len() as u32);
+    for obstacle in &world.obstacles {
+        out.vec3(&obstacle.center);
+        out.f32(obstacle.radius);
+    }
+
+    out.u32(world.nests.len() as u32);
+    for nest in &world.nests {
+        out.u32(nest.id);
+        out.vec3(&nest.position);
+        out.f32s(&[nest.radius, nest.stock, nest.food_collected]);
+    }
+
+    out.u32(world.food_sources.len() as u32);
+    for food in &world.food_sources {
+        out.u32(food.id);
+        out.vec3(&food.position);
+        out.f32s(&[food.radius, food.stock, food.capacity, food.regeneration_rate]);
+    }
+
+    out.f32(world.pheromones.voxel_size());
+    for channel in Channel::ALL {
+        let params = &world.pheromones.params[channel.index()];
+        out.f32s(&[params.diffusion, params.evaporation, params.min_value]);
+        // Sorted, so equal worlds give equal snapshots.
+        let mut chunks: Vec<_> = world.pheromones.channel(channel).chunks().collect();
+        chunks.sort_by_key(|(key, _)| (key.x, key.y, key.z));
+        out.u32(chunks.len() as u32);
+        for (key, values) in chunks {
+            out.i32s(&[key.x, key.y, key.z]);
+            out.f32s(values);
+        }
+    }
+
+    out.u32(world.ants.len() as u32);
+    for ant in &world.ants {
+        write_ant(out, ant);
+    }
+}
+
+fn read_world(input: &mut Reader) -> Result<World, String> {
+    let seed = input.u64()?;
+    let tick = input.u64()?;
+    let time = input.f64()?;
+    let bounds_radius = input.f32()?;
+    let next_ant_id = input.u32()?;
+    let next_entity_id = input.u32()?;
+    let rng = read_rng(input)?;
+
+    let mut castes = CasteTable::default();
+    for caste in Caste::ALL {
+        *castes.get_mut(caste) = read_profile(input)?;
+    }
+
+    let mut world = World::new(bounds_radius, seed);
+    world.set_castes(castes);
+    world.tick = tick;
+    world.time = time;
+    world.next_ant_id = next_ant_id;
+    world.next_entity_id = next_entity_id;
+    world.rng = rng;
+
+    world.foraging = ForagingStats::default();
+    world.foraging.delivered = input.f32()?;
+    world.foraging.trips = input.u32()?;
+    world.foraging.recent_income = input.f32()?;
+
+    for _ in 0..input.count()? {
+        world.obstacles.push(Obstacle { center: input.vec3()?, radius: input.f32()? });
+    }
+
+    for _ in 0..input.count()? {
+        world.nests.push(Nest {
+            id: input.u32()?,
+            position: input.vec3()?,
+            radius: input.f32()?,
+            stock: input.f32()?,
+            food_collected: input.f32()?,
+        });
+    }
+
+    for _ in 0..input.count()? {
+        world.food_sources.push(FoodSource {
+            id: input.u32()?,
+            position: input.vec3()?,
+            radius: input.f32()?,
+            stock: input.f32()?,
+            capacity: input.f32()?,
+            regeneration_rate: input.f32()?,
+        });
+    }
+
+    world.pheromones = PheromoneField::new(input.f32()?);
+    for channel in Channel::ALL {
+        world.pheromones.params[channel.index()] =
+            ChannelParams { diffusion: input.f32()?, evaporation: input.f32()?, min_value: input.f32()? };
+        for _ in 0..input.count()? {
+            let key = glm::vec3(input.i32()?, input.i32()?, input.i32()?);
+            let mut values = Box::new([0.0; CHUNK_VOXELS]);
+            for value in values.iter_mut() {
+                *value = input.f32()?;
+            }
+            world.pheromones.restore_chunk(channel, key, values);
+        }
+    }
+
+    for _ in 0..input.count()? {
+        world.ants.push(read_ant(input)?);
+    }
+    world.rebuild_spatial_index();
+    Ok(world)
+}
+
+pub(super) fn write_profile(out: &mut Writer, profile: &CasteProfile) {
+    let sensitivity = &profile.sensitivity;
+    let steering = &profile.steering;
+    out.f32s(&[
+        profile.max_speed,
+        profile.max_acceleration,
+        profile.turn_rate,
+        profile.max_bank,
+        profile.sensing_radius,
+        sensitivity.trail,
+        sensitivity.alarm,
+        sensitivity.recruitment,
+        sensitivity.home,
+        profile.carrying_capacity,
+        profile.lifespan,
+        profile.energy_burn_rate,
+        steering.seek,
+        steering.flee,
+        steering.wander,
+        steering.pheromone,
+        steering.avoid_obstacles,
+        steering.separation,
+        steering.alignment,
+        steering.cohesion,
+    ]);
+}
+
+pub(super) fn read_profile(input: &mut Reader) -> Result<CasteProfile, String> {
+    Ok(CasteProfile {
+        max_speed: input.f32()?,
+        max_acceleration: input.f32()?,
+        turn_rate: input.f32()?,
+        max_bank: input.f32()?,
+        sensing_radius: input.f32()?,
+        sensitivity: PheromoneSensitivity {
+            trail: input.f32()?,
+            alarm: input.f32()?,
+            recruitment: input.f32()?,
+            home: input.f32()?,
+        },
+        carrying_capacity: input.f32()?,
+        lifespan: input.f32()?,
+        energy_burn_rate: input.f32()?,
+        steering: SteeringWeights {
+            seek: input.f32()?,
+            flee: input.f32()?,
+            wander: input.f32()?,
+            pheromone: input.f32()?,
+            avoid_obstacles: input.f32()?,
+            separation: input.f32()?,
+            alignment: input.f32()?,
+            cohesion: input.f32()?,
+        },
+    })
+}
+
+fn write_ant(out: &mut Writer, ant: &Ant) {
+    out.u32(ant.id);
+    out.u8(ant.caste.index() as u8);
+    out.vec3(&ant.position);
+    out.vec3(&ant.velocity);
+    out.f32s(ant.orientation.coords.as_slice());
+    out.f32s(&[ant.energy, ant.age, ant.bank]);
+    out.vec3(&ant.wander_dir);
+    out.optional_vec3(&ant.target);
+    out.optional_vec3(&ant.threat);
+    match ant.nest {
+        Some(nest) => {
+            out.u8(1);
+            out.u32(nest);
+        },
+        None => out.u8(0),
+    }
+    match ant.state {
+        ForageState::Searching => out.u8(0),
+        ForageState::Returning => out.u8(1),
+        ForageState::Recruiting { remaining } => {
+            out.u8(2);
+            out.f32(remaining);
+        },
+    }
+    out.f32(ant.carrying);
+    out.optional_vec3(&ant.food_memory);
+    write_rng(out, &ant.rng);
+}
+
+fn read_ant(input: &mut Reader) -> Result<Ant, String> {
+    let id = input.u32()?;
+    let caste = *Caste::ALL.get(input.u8()? as usize).ok_or("Invalid caste")?;
+    let position = input.vec3()?;
+    let velocity = input.vec3()?;
+    let orientation = glm::quat(input.f32()?, input.f32()?, input.f32()?, input.f32()?);
+    let (energy, age, bank) = (input.f32()?, input.f32()?, input.f32()?);
+    let wander_dir = input.vec3()?;
+    let target = input.optional_vec3()?;
+    let threat = input.optional_vec3()?;
+    let nest = if input.flag()? { Some(input.u32()?) } else { None };
+    let state = match input.u8()? {
+        0 => ForageState::Searching,
+        1 => ForageState::Returning,
+        2 => ForageState::Recruiting { remaining: input.f32()? },
+        other => return Err(format!("Invalid foraging state {}", other)),
+    };
+    let carrying = input.f32()?;
+    let food_memory = input.optional_vec3()?;
+    let rng = read_rng(input)?;
+
+    let mut ant = Ant::new(id, caste, position, velocity, rng);
+    ant.orientation = orientation;
+    ant.energy = energy;
+    ant.age = age;
+    ant.bank = bank;
+    ant.wander_dir = wander_dir;
+    ant.target = target;
+    ant.threat = threat;
+    ant.nest = nest;
+    ant.state = state;
+    ant.carrying = carrying;
+    ant.food_memory = food_memory;
+    Ok(ant)
+}
+
+fn write_rng(out: &mut Writer, rng: &SimRng) {
+    out.bytes(&rng.get_seed());
+    out.u64(rng.get_stream());
+    out.bytes(&rng.get_word_pos().to_le_bytes());
+}
+
+fn read_rng(input: &mut Reader) -> Result<SimRng, String> {
+    use rand::SeedableRng;
+
+    let mut rng = SimRng::from_seed(input.take(32)?.try_into().unwrap());
+    rng.set_stream(input.u64()?);
+    rng.set_word_pos(u128::from_le_bytes(input.take(16)?.try_into().unwrap()));
+    Ok(rng)
+}
+
+pub(super) struct Writer(pub(super) Vec<u8>);
+
+impl Writer {
+    pub(super) fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub(super) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(super) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(super) fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(super) fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(super) fn f64(&mut self, value: f64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(super) fn f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.f32(*value);
+        }
+    }
+
+    pub(super) fn i32s(&mut self, values: &[i32]) {
+        for value in values {
+            self.bytes(&value.to_le_bytes());
+        }
+    }
+
+    pub(super) fn vec3(&mut self, value: &glm::Vec3) {
+        self.f32s(value.as_slice());
+    }
+
+    pub(super) fn optional_vec3(&mut self, value: &Option<glm::Vec3>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.vec3(value);
+            },
+            None => self.u8(0),
+        }
+    }
+}
+
+pub(super) struct Reader<'a> {
+    pub(super) bytes: &'a [u8],
+    pub(super) at: usize,
+}
+
+impl Reader<'_> {
+    pub(super) fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self.bytes.get(self.at..self.at + len).ok_or("Snapshot is truncated")?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn flag(&mut self) -> Result<bool, String> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(format!("Invalid flag {}", other)),
+        }
+    }
+
+    pub(super) fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Element count, checked against the bytes left so a corrupt count fails
+    /// cleanly instead of looping for ages.
+    pub(super) fn count(&mut self) -> Result<u32, String> {
+        let count = self.u32()?;
+        if count as usize > self.bytes.len() - self.at {
+            return Err("Snapshot is truncated".to_string());
+        }
+        Ok(count)
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(super) fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(super) fn vec3(&mut self) -> Result<glm::Vec3, String> {
+        Ok(glm::vec3(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    pub(super) fn optional_vec3(&mut self) -> Result<Option<glm::Vec3>, String> {
+        Ok(if self.flag()? { Some(self.vec3()?) } else { None })
+    }
+}
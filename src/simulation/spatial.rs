@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+
+use nalgebra_glm as glm;
+
+/// Multiply-rotate hasher for small integer keys. Much cheaper than the default
+/// SipHash, and the grid is never fed untrusted keys.
+#[derive(Default)]
+pub struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.write_u64(value as u32 as u64);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub type CellMap<V> = HashMap<glm::IVec3, V, BuildHasherDefault<CellHasher>>;
+type CellSet = HashSet<glm::IVec3, BuildHasherDefault<CellHasher>>;
+
+/// Grids larger than this many cells (e.g. because of a far-away outlier) are
+/// rebuilt with coarser cells instead.
+const MAX_CELLS: usize = 1 << 22;
+
+/// Uniform grid over points, rebuilt from scratch every tick with a counting
+/// sort over the bounding box of the points. Items are identified by their
+/// index in the sequence passed to `rebuild`.
+pub struct SpatialGrid {
+    /// Requested cell size; `cell_size` may be coarser after a sparse rebuild.
+    base_cell_size: f32,
+    cell_size: f32,
+    positions: Vec<glm::Vec3>,
+    min_cell: glm::IVec3,
+    dims: glm::IVec3,
+    /// Item indices grouped by cell; cell `c` owns `items[starts[c]..starts[c + 1]]`.
+    items: Vec<u32>,
+    /// Copy of `positions` in the order of `items`, so a cell scan reads memory linearly.
+    item_positions: Vec<glm::Vec3>,
+    starts: Vec<u32>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid {
+            base_cell_size: cell_size,
+            cell_size,
+            positions: Vec::new(),
+            min_cell: glm::IVec3::zeros(),
+            dims: glm::IVec3::zeros(),
+            items: Vec::new(),
+            item_positions: Vec::new(),
+            starts: vec![0],
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn position(&self, index: usize) -> &glm::Vec3 {
+        &self.positions[index]
+    }
+
+    pub fn rebuild<I: IntoIterator<Item = glm::Vec3>>(&mut self, positions: I) {
+        self.positions.clear();
+        self.positions.extend(positions);
+
+        self.cell_size = self.base_cell_size;
+        if self.positions.is_empty() {
+            self.dims = glm::IVec3::zeros();
+        } else {
+            let (min, max) = self.positions.iter().fold(
+                (self.positions[0], self.positions[0]),
+                |(min, max), p| (glm::min2(&min, p), glm::max2(&max, p)),
+            );
+            loop {
+                self.min_cell = cell_of(&min, self.cell_size);
+                self.dims = cell_of(&max, self.cell_size) - self.min_cell + glm::vec3(1, 1, 1);
+                if self.dims.iter().map(|d| *d as usize).product::<usize>() <= MAX_CELLS {
+                    break;
+                }
+                self.cell_size *= 2.0;
+            }
+        }
+
+        let cell_count = self.dims.iter().map(|d| *d as usize).product::<usize>();
+        self.starts.clear();
+        self.starts.resize(cell_count + 1, 0);
+        for position in &self.positions {
+            let cell = self.cell_index(&cell_of(position, self.cell_size)).unwrap();
+            self.starts[cell + 1] += 1;
+        }
+        for cell in 0..cell_count {
+            self.starts[cell + 1] += self.starts[cell];
+        }
+
+        self.items.clear();
+        self.items.resize(self.positions.len(), 0);
+        self.item_positions.clear();
+        self.item_positions.resize(self.positions.len(), glm::Vec3::zeros());
+        let mut fill = self.starts.clone();
+        for (index, position) in self.positions.iter().enumerate() {
+            let cell = self.cell_index(&cell_of(position, self.cell_size)).unwrap();
+            self.items[fill[cell] as usize] = index as u32;
+            self.item_positions[fill[cell] as usize] = *position;
+            fill[cell] += 1;
+        }
+    }
+
+    fn cell_index(&self, cell: &glm::IVec3) -> Option<usize> {
+        let local = cell - self.min_cell;
+        if (0..3).any(|a| local[a] < 0 || local[a] >= self.dims[a]) {
+            return None;
+        }
+        Some((local.x + self.dims.x * (local.y + self.dims.y * local.z)) as usize)
+    }
+
+    /// `(index, position)` of every item in `cell`.
+    fn cell_items(&self, cell: &glm::IVec3) -> impl Iterator<Item = (usize, &glm::Vec3)> {
+        let range = match self.cell_index(cell) {
+            Some(index) => self.starts[index] as usize..self.starts[index + 1] as usize,
+            None => 0..0,
+        };
+        self.items[range.clone()].iter().map(|index| *index as usize).zip(&self.item_positions[range])
+    }
+
+    /// Calls `visit(index, distance²)` for every item within `radius` of `center`.
+    pub fn for_each_in_radius<F: FnMut(usize, f32)>(&self, center: &glm::Vec3, radius: f32, mut visit: F) {
+        if self.positions.is_empty() {
+            return;
+        }
+        let radius2 = radius * radius;
+        // Only visit occupied cells, however large the radius.
+        let last_cell = self.min_cell + self.dims - glm::vec3(1, 1, 1);
+        let min = glm::max2(&cell_of(&(center - glm::vec3(radius, radius, radius)), self.cell_size), &self.min_cell);
+        let max = glm::min2(&cell_of(&(center + glm::vec3(radius, radius, radius)), self.cell_size), &last_cell);
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    for (index, position) in self.cell_items(&glm::vec3(x, y, z)) {
+                        let dist2 = glm::distance2(position, center);
+                        if dist2 <= radius2 {
+                            visit(index, dist2);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Indices of every item within `radius` of `center`, in no particular order.
+    pub fn query_radius(&self, center: &glm::Vec3, radius: f32, out: &mut Vec<usize>) {
+        out.clear();
+        self.for_each_in_radius(center, radius, |index, _| out.push(index));
+    }
+
+    /// Up to `k` items closest to `center` and no further than `max_radius`, as
+    /// `(index, distance)` sorted nearest first. Searches outwards one shell of
+    /// cells at a time and stops once no unvisited cell can hold anything closer.
+    pub fn k_nearest(&self, center: &glm::Vec3, k: usize, max_radius: f32) -> Vec<(usize, f32)> {
+        let mut found: Vec<(usize, f32)> = Vec::with_capacity(k + 1);
+        if k == 0 || self.positions.is_empty() {
+            return found;
+        }
+        let origin = cell_of(center, self.cell_size);
+        // Rings past the one enclosing every occupied cell are empty.
+        let last_cell = self.min_cell + self.dims - glm::vec3(1, 1, 1);
+        let enclosing = (origin - self.min_cell).abs().max().max((last_cell - origin).abs().max());
+        let max_ring = ((max_radius / self.cell_size).ceil() as i32).saturating_add(1).min(enclosing);
+        let max_radius2 = max_radius * max_radius;
+
+        // Offsets from `origin` of the occupied cells.
+        let (low, high) = (self.min_cell - origin, last_cell - origin);
+        for ring in 0..=max_ring {
+            for z in (-ring).max(low.z)..=ring.min(high.z) {
+                for y in (-ring).max(low.y)..=ring.min(high.y) {
+                    for x in (-ring).max(low.x)..=ring.min(high.x) {
+                        if x.abs().max(y.abs()).max(z.abs()) != ring {
+                            continue;
+                        }
+                        for (index, position) in self.cell_items(&(origin + glm::vec3(x, y, z))) {
+                            let dist2 = glm::distance2(position, center);
+                            if dist2 > max_radius2 || (found.len() == k && dist2 >= found[k - 1].1) {
+                                continue;
+                            }
+                            let at = found.partition_point(|(_, d)| *d <= dist2);
+                            found.insert(at, (index, dist2));
+                            found.truncate(k);
+                        }
+                    }
+                }
+            }
+            // Anything outside the cube of cells searched so far is at least
+            // `reach` away from the centre.
+            let low = (origin - glm::vec3(ring, ring, ring)).map(|c| c as f32 * self.cell_size);
+            let high = (origin + glm::vec3(ring + 1, ring + 1, ring + 1)).map(|c| c as f32 * self.cell_size);
+            let reach = (center - low).min().min((high - center).min());
+            if found.len() == k && found[k - 1].1 <= reach * reach {
+                break;
+            }
+        }
+
+        for entry in &mut found {
+            entry.1 = entry.1.sqrt();
+        }
+        found
+    }
+
+    /// First item whose bounding sphere of `item_radius` is hit by the ray
+    /// `origin + t * direction`, `0 <= t <= max_distance`, as `(index, t)`.
+    /// Walks the cells along the ray (3D DDA), also testing the neighbouring
+    /// cells since spheres may overhang the cell their centre is in. Only the
+    /// stretch of the ray through the occupied cells is walked.
+    pub fn raycast(
+        &self,
+        origin: &glm::Vec3,
+        direction: &glm::Vec3,
+        max_distance: f32,
+        item_radius: f32,
+    ) -> Option<(usize, f32)> {
+        if glm::length2(direction) < 1e-12 || self.positions.is_empty() {
+            return None;
+        }
+        let dir = glm::normalize(direction);
+        let spread = (item_radius / self.cell_size).ceil() as i32;
+
+        // Every hit lies in the occupied cells grown by the item radius.
+        let margin = glm::vec3(item_radius, item_radius, item_radius);
+        let low = self.min_cell.map(|c| c as f32) * self.cell_size - margin;
+        let high = (self.min_cell + self.dims).map(|c| c as f32) * self.cell_size + margin;
+        let (enter, exit) = ray_box(origin, &dir, &low, &high)?;
+        let end = exit.min(max_distance);
+        if enter > end {
+            return None;
+        }
+        let start = origin + dir * enter;
+
+        let mut cell = cell_of(&start, self.cell_size);
+        let step = dir.map(|d| if d > 0.0 { 1 } else if d < 0.0 { -1 } else { 0 });
+        let mut t_max = glm::Vec3::zeros();
+        let mut t_delta = glm::Vec3::zeros();
+        for a in 0..3 {
+            if step[a] == 0 {
+                t_max[a] = f32::INFINITY;
+                t_delta[a] = f32::INFINITY;
+            } else {
+                let boundary = (cell[a] + (step[a] > 0) as i32) as f32 * self.cell_size;
+                t_max[a] = enter + (boundary - start[a]) / dir[a];
+                t_delta[a] = self.cell_size / dir[a].abs();
+            }
+        }
+
+        let mut visited = CellSet::default();
+        let mut best: Option<(usize, f32)> = None;
+        let mut t = enter;
+        while t <= end {
+            for z in -spread..=spread {
+                for y in -spread..=spread {
+                    for x in -spread..=spread {
+                        let neighbour = cell + glm::vec3(x, y, z);
+                        if !visited.insert(neighbour) {
+                            continue;
+                        }
+                        for (index, position) in self.cell_items(&neighbour) {
+                            let hit = ray_sphere(origin, &dir, position, item_radius);
+                            if let Some(hit) = hit.filter(|hit| *hit <= max_distance && best.is_none_or(|(_, b)| *hit < b)) {
+                                best = Some((index, hit));
+                            }
+                        }
+                    }
+                }
+            }
+            // Nothing further along the ray can beat a hit before the next cell.
+            let next = t_max.min();
+            if best.is_some_and(|(_, b)| b <= next) {
+                break;
+            }
+            let axis = t_max.imin();
+            cell[axis] += step[axis];
+            t = t_max[axis];
+            t_max[axis] += t_delta[axis];
+        }
+        best
+    }
+}
+
+/// Distances along a ray at which it enters and leaves the box from `low` to
+/// `high`, the entry clamped to 0, or `None` if it misses the box.
+fn ray_box(origin: &glm::Vec3, dir: &glm::Vec3, low: &glm::Vec3, high: &glm::Vec3) -> Option<(f32, f32)> {
+    let (mut enter, mut exit) = (0.0f32, f32::INFINITY);
+    for a in 0..3 {
+        if dir[a] == 0.0 {
+            if origin[a] < low[a] || origin[a] > high[a] {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((low[a] - origin[a]) / dir[a], (high[a] - origin[a]) / dir[a]);
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+    }
+    (enter <= exit).then_some((enter, exit))
+}
+
+/// Distance along a normalised ray to the first intersection with a sphere,
+/// or 0 if the origin is inside it.
+pub fn ray_sphere(origin: &glm::Vec3, dir: &glm::Vec3, center: &glm::Vec3, radius: f32) -> Option<f32> {
+    let to_center = center - origin;
+    let along = glm::dot(&to_center, dir);
+    let dist2 = glm::length2(&to_center) - along * along;
+    let radius2 = radius * radius;
+    if dist2 > radius2 {
+        return None;
+    }
+    let half_chord = (radius2 - dist2).sqrt();
+    let (near, far) = (along - half_chord, along + half_chord);
+    if far < 0.0 {
+        None
+    } else {
+        Some(near.max(0.0))
+    }
+}
+
+fn cell_of(position: &glm::Vec3, cell_size: f32) -> glm::IVec3 {
+    position.map(|c| (c / cell_size).floor() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::rng::{random_in_sphere, substream};
+
+    fn grid() -> SpatialGrid {
+        let mut rng = substream(1, 0);
+        let mut grid = SpatialGrid::new(0.25);
+        grid.rebuild((0..500).map(|_| random_in_sphere(&mut rng, 3.0)));
+        grid
+    }
+
+    #[test]
+    fn radius_query_matches_brute_force_for_any_radius() {
+        let grid = grid();
+        let center = glm::vec3(0.5, -0.2, 1.0);
+        for radius in [0.0, 0.3, 2.0, 1e6, f32::MAX] {
+            let mut found = Vec::new();
+            grid.query_radius(&center, radius, &mut found);
+            found.sort_unstable();
+            let expected: Vec<usize> =
+                (0..grid.len()).filter(|&i| glm::distance2(grid.position(i), &center) <= radius * radius).collect();
+            assert_eq!(found, expected, "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn k_nearest_with_huge_radius_finds_the_closest() {
+        let grid = grid();
+        let center = glm::vec3(50.0, 0.0, 0.0);
+        let found = grid.k_nearest(&center, 3, f32::MAX);
+        let mut expected: Vec<(usize, f32)> = (0..grid.len()).map(|i| (i, glm::distance(grid.position(i), &center))).collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let indices: Vec<usize> = found.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, expected[..3].iter().map(|(i, _)| *i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn raycast_from_outside_matches_brute_force() {
+        let grid = grid();
+        let origin = glm::vec3(-1000.0, 0.3, -0.1);
+        for direction in [glm::vec3(1.0, 0.0, 0.0), glm::vec3(1.0, 0.001, 0.0), glm::vec3(-1.0, 0.0, 0.0)] {
+            let dir = glm::normalize(&direction);
+            let expected = (0..grid.len())
+                .filter_map(|i| Some((i, ray_sphere(&origin, &dir, grid.position(i), 0.1)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let hit = grid.raycast(&origin, &direction, f32::MAX, 0.1);
+            assert_eq!(hit.map(|(i, _)| i), expected.map(|(i, _)| i), "direction {:?}", direction);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+
+use super::caste::Caste;
+use super::pheromone::Channel;
+use super::world::World;
+
+/// Samples kept by the windowed app: ten minutes at one sample every six ticks.
+pub const DEFAULT_CAPACITY: usize = 6000;
+pub const DEFAULT_SAMPLE_EVERY: u64 = 6;
+
+/// Colony-wide figures at one tick.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub tick: u64,
+    pub time: f64,
+    /// Living ants, indexed by `Caste`.
+    pub population: [u32; Caste::ALL.len()],
+    /// Food delivered to any nest since the start of the run.
+    pub food_collected: f32,
+    /// Mean energy of the living ants, 0 without any.
+    pub average_energy: f32,
+    /// Total pheromone, indexed by `Channel`.
+    pub pheromone_mass: [f32; Channel::ALL.len()],
+    /// Ants spawned and ants that died since the previous sample.
+    pub births: u32,
+    pub deaths: u32,
+    /// Ticks since the previous sample, to turn `births` and `deaths` into rates.
+    pub ticks: u64,
+}
+
+impl Sample {
+    pub fn total_population(&self) -> u32 {
+        self.population.iter().sum()
+    }
+
+    pub fn births_per_tick(&self) -> f32 {
+        self.births as f32 / self.ticks.max(1) as f32
+    }
+
+    pub fn deaths_per_tick(&self) -> f32 {
+        self.deaths as f32 / self.ticks.max(1) as f32
+    }
+}
+
+/// Records a `Sample` of a world every `sample_every` ticks into a ring buffer
+/// of bounded size, dropping the oldest samples once full.
+#[derive(Clone, Debug)]
+pub struct Stats {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+    sample_every: u64,
+    /// State of the world at the last sample, to count births and deaths.
+    last_tick: Option<u64>,
+    last_next_ant_id: u32,
+    last_ants: usize,
+    total_births: u64,
+    total_deaths: u64,
+}
+
+impl Stats {
+    pub fn new(capacity: usize, sample_every: u64) -> Self {
+        Stats {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            sample_every: sample_every.max(1),
+            last_tick: None,
+            last_next_ant_id: 0,
+            last_ants: 0,
+            total_births: 0,
+            total_deaths: 0,
+        }
+    }
+
+    pub fn sample_every(&self) -> u64 {
+        self.sample_every
+    }
+
+    pub fn samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    /// Ants spawned and ants that died over everything recorded, including
+    /// samples already dropped from the buffer.
+    pub fn total_births(&self) -> u64 {
+        self.total_births
+    }
+
+    pub fn total_deaths(&self) -> u64 {
+        self.total_deaths
+    }
+
+    /// Takes a sample if `world` is at least `sample_every` ticks past the
+    /// last one. Can be called every tick or less often; births and deaths
+    /// are counted across the gap. Returns whether it took one.
+    pub fn record(&mut self, world: &World) -> bool {
+        let due = self.last_tick.is_none_or(|last| world.tick < last || world.tick >= last + self.sample_every);
+        if due {
+            self.sample(world);
+        }
+        due
+    }
+
+    /// Takes a sample of `world` now. A world behind the last sample, e.g.
+    /// after seeking back or loading, first drops the samples from its tick
+    /// on and starts counting births and deaths again from there.
+    pub fn sample(&mut self, world: &World) {
+        if self.last_tick.is_some_and(|last| world.tick < last) {
+            while let Some(sample) = self.samples.back().filter(|sample| sample.tick >= world.tick) {
+                self.total_births -= sample.births as u64;
+                self.total_deaths -= sample.deaths as u64;
+                self.samples.pop_back();
+            }
+            self.last_tick = None;
+        }
+
+        let (births, deaths, ticks) = match self.last_tick {
+            Some(last) => {
+                // Ids are handed out in order, so everything at or past the
+                // last next id was born since.
+                let births = world.ants.iter().filter(|ant| ant.id >= self.last_next_ant_id).count();
+                let deaths = (self.last_ants + births).saturating_sub(world.ants.len());
+                (births as u32, deaths as u32, world.tick - last)
+            },
+            None => (0, 0, 0),
+        };
+        self.total_births += births as u64;
+        self.total_deaths += deaths as u64;
+        self.last_tick = Some(world.tick);
+        self.last_next_ant_id = world.next_ant_id;
+        self.last_ants = world.ants.len();
+
+        let mut population = [0; Caste::ALL.len()];
+        let mut energy = 0.0;
+        for ant in &world.ants {
+            population[ant.caste.index()] += 1;
+            energy += ant.energy;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            tick: world.tick,
+            time: world.time,
+            population,
+            food_collected: world.foraging.delivered,
+            average_energy: if world.ants.is_empty() { 0.0 } else { energy / world.ants.len() as f32 },
+            pheromone_mass: Channel::ALL.map(|channel| world.pheromones.total_mass(channel)),
+            births,
+            deaths,
+            ticks,
+        });
+    }
+
+    /// Forgets every sample, e.g. when starting over with another world.
+    pub fn clear(&mut self) {
+        *self = Stats::new(self.capacity, self.sample_every);
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new(DEFAULT_CAPACITY, DEFAULT_SAMPLE_EVERY)
+    }
+}
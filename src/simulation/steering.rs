@@ -0,0 +1,342 @@
+use nalgebra_glm as glm;
+use serde::Deserialize;
+
+use super::ant::Ant;
+use super::caste::CasteProfile;
+use super::pheromone::{Channel, PheromoneField};
+use super::world::Obstacle;
+
+/// Everything a behavior may look at when steering one ant.
+pub struct SteeringContext<'a> {
+    pub ant: &'a Ant,
+    pub profile: &'a CasteProfile,
+    /// The closest few other ants within `profile.sensing_radius`.
+    pub neighbours: &'a [&'a Ant],
+    pub pheromones: &'a PheromoneField,
+    pub obstacles: &'a [Obstacle],
+    /// Radius of the sphere around the origin the ant must stay inside.
+    pub bounds_radius: f32,
+}
+
+impl SteeringContext<'_> {
+    /// Steering force that turns the current velocity into `direction * max_speed`.
+    fn steer_towards(&self, direction: &glm::Vec3) -> glm::Vec3 {
+        if glm::length2(direction) < 1e-12 {
+            return glm::Vec3::zeros();
+        }
+        glm::normalize(direction) * self.profile.max_speed - self.ant.velocity
+    }
+}
+
+/// A single steering rule. Returns the acceleration it would like to apply.
+/// Behaviors are shared between the threads of the steering pass.
+pub trait SteeringBehavior: Send + Sync {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3;
+}
+
+/// Flies towards `Ant::target`.
+pub struct Seek;
+
+impl SteeringBehavior for Seek {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        match ctx.ant.target {
+            Some(target) => ctx.steer_towards(&(target - ctx.ant.position)),
+            None => glm::Vec3::zeros(),
+        }
+    }
+}
+
+/// Flies away from `Ant::threat` while it is closer than `panic_radius`.
+pub struct Flee {
+    pub panic_radius: f32,
+}
+
+impl SteeringBehavior for Flee {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        match ctx.ant.threat {
+            Some(threat) if glm::distance(&threat, &ctx.ant.position) < self.panic_radius => {
+                ctx.steer_towards(&(ctx.ant.position - threat))
+            },
+            _ => glm::Vec3::zeros(),
+        }
+    }
+}
+
+/// Aims at a point on a sphere projected ahead of the ant. The point is
+/// `Ant::wander_dir`, which the world jitters a little every tick.
+pub struct Wander {
+    pub distance: f32,
+    pub radius: f32,
+}
+
+impl SteeringBehavior for Wander {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        let forward = ctx.ant.forward();
+        ctx.steer_towards(&(forward * self.distance + ctx.ant.wander_dir * self.radius))
+    }
+}
+
+/// Climbs the concentration gradient of one pheromone channel, scaled by the
+/// caste's sensitivity to it. Ignores channels the ant's foraging state is not
+/// interested in.
+pub struct FollowPheromone {
+    pub channel: Channel,
+}
+
+impl SteeringBehavior for FollowPheromone {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        let sensitivity = ctx.profile.sensitivity.get(self.channel);
+        if sensitivity == 0.0 || !ctx.ant.state.attracted_to(self.channel) {
+            return glm::Vec3::zeros();
+        }
+        let gradient = ctx.pheromones.gradient(self.channel, &ctx.ant.position);
+        let strength = glm::length(&gradient).min(1.0);
+        ctx.steer_towards(&gradient) * strength * sensitivity
+    }
+}
+
+/// Steers sideways around spherical obstacles found within `look_ahead`
+/// seconds of travel along the current velocity, and back inwards if that
+/// travel would leave the world bounds.
+pub struct AvoidObstacles {
+    pub look_ahead: f32,
+}
+
+impl SteeringBehavior for AvoidObstacles {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        let speed = glm::length(&ctx.ant.velocity);
+        if speed < 1e-6 {
+            return glm::Vec3::zeros();
+        }
+        let forward = ctx.ant.velocity / speed;
+        let reach = speed * self.look_ahead;
+
+        let mut nearest: Option<(f32, &Obstacle)> = None;
+        for obstacle in ctx.obstacles {
+            let to_center = obstacle.center - ctx.ant.position;
+            let along = glm::dot(&to_center, &forward);
+            if along < 0.0 || along > reach + obstacle.radius {
+                continue;
+            }
+            let lateral = glm::length(&(to_center - forward * along));
+            if lateral < obstacle.radius && nearest.is_none_or(|(d, _)| along < d) {
+                nearest = Some((along, obstacle));
+            }
+        }
+
+        match nearest {
+            Some((along, obstacle)) => {
+                let closest = ctx.ant.position + forward * along;
+                let mut away = closest - obstacle.center;
+                if glm::length2(&away) < 1e-8 {
+                    away = glm::cross(&forward, &ctx.ant.up());
+                }
+                ctx.steer_towards(&away) * (1.0 - along / (reach + obstacle.radius))
+            },
+            None => {
+                let overshoot = glm::length(&(ctx.ant.position + forward * reach)) - ctx.bounds_radius;
+                if overshoot > 0.0 {
+                    ctx.steer_towards(&(-ctx.ant.position)) * (overshoot / reach).min(1.0)
+                } else {
+                    glm::Vec3::zeros()
+                }
+            },
+        }
+    }
+}
+
+/// Pushes away from neighbours, harder the closer they are.
+pub struct Separation;
+
+impl SteeringBehavior for Separation {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        let mut push = glm::Vec3::zeros();
+        for other in ctx.neighbours {
+            let offset = ctx.ant.position - other.position;
+            let dist2 = glm::length2(&offset);
+            if dist2 > 1e-8 {
+                push += offset / dist2;
+            }
+        }
+        ctx.steer_towards(&push)
+    }
+}
+
+/// Matches the average heading of neighbours.
+pub struct Alignment;
+
+impl SteeringBehavior for Alignment {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        let heading: glm::Vec3 = ctx.neighbours.iter().map(|other| other.velocity).sum();
+        ctx.steer_towards(&heading)
+    }
+}
+
+/// Flies towards the centre of mass of neighbours.
+pub struct Cohesion;
+
+impl SteeringBehavior for Cohesion {
+    fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        if ctx.neighbours.is_empty() {
+            return glm::Vec3::zeros();
+        }
+        let center: glm::Vec3 = ctx.neighbours.iter().map(|other| other.position).sum::<glm::Vec3>()
+            / ctx.neighbours.len() as f32;
+        ctx.steer_towards(&(center - ctx.ant.position))
+    }
+}
+
+/// Per-caste behavior weights, as read from the caste data file. A weight of 0
+/// leaves the behavior out of the pipeline.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SteeringWeights {
+    pub seek: f32,
+    pub flee: f32,
+    pub wander: f32,
+    /// Multiplied by the caste's per-channel pheromone sensitivity.
+    pub pheromone: f32,
+    pub avoid_obstacles: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+/// Weighted sum of steering behaviors used for one caste.
+#[derive(Default)]
+pub struct SteeringPipeline {
+    behaviors: Vec<(Box<dyn SteeringBehavior>, f32)>,
+}
+
+impl SteeringPipeline {
+    pub fn from_weights(weights: &SteeringWeights) -> Self {
+        let mut pipeline = SteeringPipeline::default();
+        pipeline.add(Box::new(AvoidObstacles { look_ahead: 1.5 }), weights.avoid_obstacles);
+        pipeline.add(Box::new(Flee { panic_radius: 1.0 }), weights.flee);
+        pipeline.add(Box::new(Seek), weights.seek);
+        for channel in Channel::ALL {
+            pipeline.add(Box::new(FollowPheromone { channel }), weights.pheromone);
+        }
+        pipeline.add(Box::new(Separation), weights.separation);
+        pipeline.add(Box::new(Alignment), weights.alignment);
+        pipeline.add(Box::new(Cohesion), weights.cohesion);
+        pipeline.add(Box::new(Wander { distance: 1.0, radius: 0.5 }), weights.wander);
+        pipeline
+    }
+
+    /// Appends a behavior. Zero-weighted behaviors are dropped.
+    pub fn add(&mut self, behavior: Box<dyn SteeringBehavior>, weight: f32) {
+        if weight != 0.0 {
+            self.behaviors.push((behavior, weight));
+        }
+    }
+
+    pub fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+        self.behaviors.iter().map(|(behavior, weight)| behavior.steer(ctx) * *weight).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::caste::Caste;
+    use crate::simulation::rng::substream;
+
+    fn ant(position: glm::Vec3, velocity: glm::Vec3) -> Ant {
+        Ant::new(0, Caste::Worker, position, velocity, substream(3, 0))
+    }
+
+    /// Runs `behavior` for `ant` in an empty world with the given neighbours and obstacles.
+    fn steer(behavior: &dyn SteeringBehavior, ant: &Ant, neighbours: &[&Ant], obstacles: &[Obstacle]) -> glm::Vec3 {
+        let profile = CasteProfile::default_for(ant.caste);
+        let pheromones = PheromoneField::new(0.5);
+        behavior.steer(&SteeringContext {
+            ant,
+            profile: &profile,
+            neighbours,
+            pheromones: &pheromones,
+            obstacles,
+            bounds_radius: 100.0,
+        })
+    }
+
+    fn assert_points_along(force: glm::Vec3, direction: glm::Vec3) {
+        assert!(glm::length(&force) > 1e-6, "no force, expected one along {}", direction);
+        let cos = glm::dot(&glm::normalize(&force), &glm::normalize(&direction));
+        assert!(cos > 0.99, "force {} does not point along {}", force, direction);
+    }
+
+    #[test]
+    fn seek_steers_towards_the_target() {
+        let mut seeker = ant(glm::vec3(1.0, 0.0, 0.0), glm::Vec3::zeros());
+        assert_eq!(steer(&Seek, &seeker, &[], &[]), glm::Vec3::zeros());
+        let target = glm::vec3(-2.0, 3.0, 1.0);
+        seeker.target = Some(target);
+        assert_points_along(steer(&Seek, &seeker, &[], &[]), target - seeker.position);
+    }
+
+    #[test]
+    fn flee_steers_away_from_a_close_threat_only() {
+        let flee = Flee { panic_radius: 1.0 };
+        let mut prey = ant(glm::Vec3::zeros(), glm::Vec3::zeros());
+        let threat = glm::vec3(0.3, -0.4, 0.2);
+        prey.threat = Some(threat);
+        assert_points_along(steer(&flee, &prey, &[], &[]), -threat);
+        prey.threat = Some(threat * 5.0);
+        assert_eq!(steer(&flee, &prey, &[], &[]), glm::Vec3::zeros());
+    }
+
+    #[test]
+    fn separation_steers_away_from_a_close_neighbour() {
+        let me = ant(glm::Vec3::zeros(), glm::Vec3::zeros());
+        let close = ant(glm::vec3(0.1, 0.0, 0.0), glm::Vec3::zeros());
+        let far = ant(glm::vec3(0.0, 0.0, -0.7), glm::Vec3::zeros());
+        assert_eq!(steer(&Separation, &me, &[], &[]), glm::Vec3::zeros());
+        let force = steer(&Separation, &me, &[&close, &far], &[]);
+        // Dominated by the close neighbour, with a little push from the far one.
+        assert!(force.x < 0.0 && force.z > 0.0 && force.x.abs() > force.z.abs(), "force {}", force);
+    }
+
+    #[test]
+    fn avoid_obstacles_steers_around_an_obstacle_ahead() {
+        let avoid = AvoidObstacles { look_ahead: 1.5 };
+        let flyer = ant(glm::Vec3::zeros(), glm::vec3(0.0, 0.0, 0.6));
+        let obstacle = Obstacle { center: glm::vec3(0.1, 0.0, 0.6), radius: 0.3 };
+        let force = steer(&avoid, &flyer, &[], &[obstacle]);
+        assert!(force.x < 0.0, "force {}", force);
+        let behind = Obstacle { center: glm::vec3(0.0, 0.0, -0.6), radius: 0.3 };
+        assert_eq!(steer(&avoid, &flyer, &[], &[behind]), glm::Vec3::zeros());
+    }
+
+    #[test]
+    fn zero_weight_disables_a_behavior() {
+        let mut me = ant(glm::Vec3::zeros(), glm::Vec3::zeros());
+        me.target = Some(glm::vec3(0.0, 2.0, 0.0));
+        me.threat = Some(glm::vec3(0.2, 0.0, 0.0));
+        let neighbour = ant(glm::vec3(0.0, 0.0, 0.1), glm::Vec3::zeros());
+        let neighbours = [&neighbour];
+        let pipeline = |weights: SteeringWeights| {
+            steer(&Pipeline(SteeringPipeline::from_weights(&weights)), &me, &neighbours, &[])
+        };
+
+        assert!(SteeringPipeline::from_weights(&SteeringWeights::default()).behaviors.is_empty());
+        assert_eq!(pipeline(SteeringWeights::default()), glm::Vec3::zeros());
+
+        let seek_only = SteeringWeights { seek: 2.0, ..Default::default() };
+        assert_eq!(SteeringPipeline::from_weights(&seek_only).behaviors.len(), 1);
+        assert_eq!(pipeline(seek_only), steer(&Seek, &me, &neighbours, &[]) * 2.0);
+
+        let without_flee = SteeringWeights { seek: 1.0, separation: 1.0, ..Default::default() };
+        let expected = steer(&Seek, &me, &neighbours, &[]) + steer(&Separation, &me, &neighbours, &[]);
+        assert!(glm::distance(&pipeline(without_flee), &expected) < 1e-6);
+    }
+
+    /// Lets a whole pipeline go through the same `steer` helper as single behaviors.
+    struct Pipeline(SteeringPipeline);
+
+    impl SteeringBehavior for Pipeline {
+        fn steer(&self, ctx: &SteeringContext) -> glm::Vec3 {
+            self.0.steer(ctx)
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/// Accumulates real frame time and hands it out in fixed simulation steps, so
+/// the world advances at the same rate no matter how fast frames are presented.
+pub struct FixedTimestep {
+    pub dt: f32,
+    accumulator: f32,
+    max_steps_per_frame: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(dt: f32) -> Self {
+        FixedTimestep { dt, accumulator: 0.0, max_steps_per_frame: 8 }
+    }
+
+    /// Adds `frame_time` seconds and returns how many steps of `dt` should be run.
+    /// Time beyond `max_steps_per_frame` steps is dropped so a long stall (window
+    /// drag, breakpoint) does not make the simulation spiral.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time;
+        let mut steps = 0;
+        while self.accumulator >= self.dt && steps < self.max_steps_per_frame {
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+        if steps == self.max_steps_per_frame {
+            self.accumulator = self.accumulator.min(self.dt);
+        }
+        steps
+    }
+
+    /// Fraction of a step left over in the accumulator, in `[0, 1)`.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.dt
+    }
+}
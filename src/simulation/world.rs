@@ -0,0 +1,418 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::thread;
+
+use nalgebra_glm as glm;
+use rand::Rng;
+
+use super::ant::Ant;
+use super::caste::{Caste, CasteTable};
+use super::flight;
+use super::foraging::{self, FoodSource, ForageState, ForagingStats, Nest};
+use super::pheromone::{Channel, PheromoneField};
+use super::rng::{self, random_in_sphere, random_on_sphere, SimRng};
+use super::snapshot;
+use super::spatial::SpatialGrid;
+use super::steering::{SteeringContext, SteeringPipeline};
+
+pub const FIXED_DT: f32 = 1.0 / 60.0;
+const PHEROMONE_VOXEL_SIZE: f32 = 0.25;
+/// Steering only looks at this many of the closest ants in sensing range.
+const MAX_NEIGHBOURS: usize = 8;
+/// Below this many ants the steering pass is not worth spreading over threads.
+const PARALLEL_STEERING_MIN_ANTS: usize = 2048;
+/// Radians per second the wander target drifts around its sphere.
+const WANDER_JITTER: f32 = 2.0;
+/// Alarm pheromone laid per second by an ant that senses a threat.
+const ALARM_DEPOSIT_RATE: f32 = 1.0;
+/// Size of the food sources placed by `respawn_food_sources`.
+pub const DEFAULT_FOOD_RADIUS: f32 = 0.3;
+pub const DEFAULT_FOOD_CAPACITY: f32 = 20.0;
+pub const DEFAULT_FOOD_REGENERATION: f32 = 0.2;
+
+/// Solid sphere ants steer around and cannot enter.
+#[derive(Clone, Copy, Debug)]
+pub struct Obstacle {
+    pub center: glm::Vec3,
+    pub radius: f32,
+}
+
+/// Headless state of the simulation. Holds no GL resources, so it can be
+/// stepped and inspected without a window.
+pub struct World {
+    pub ants: Vec<Ant>,
+    pub pheromones: PheromoneField,
+    castes: CasteTable,
+    steering: [SteeringPipeline; Caste::ALL.len()],
+    pub obstacles: Vec<Obstacle>,
+    pub nests: Vec<Nest>,
+    pub food_sources: Vec<FoodSource>,
+    pub foraging: ForagingStats,
+    /// Positions of `ants` as of the start of the last step, indexed like `ants`.
+    spatial: SpatialGrid,
+    /// Ants are kept inside a sphere of this radius centred on the origin.
+    pub bounds_radius: f32,
+    pub tick: u64,
+    pub time: f64,
+    pub(super) seed: u64,
+    /// World stream of `seed`; ants draw from their own streams.
+    pub(super) rng: SimRng,
+    pub(super) next_ant_id: u32,
+    pub(super) next_entity_id: u32,
+}
+
+impl World {
+    /// Empty world whose every random decision derives from `seed`.
+    pub fn new(bounds_radius: f32, seed: u64) -> Self {
+        let castes = CasteTable::default();
+        World {
+            ants: Vec::new(),
+            pheromones: PheromoneField::new(PHEROMONE_VOXEL_SIZE),
+            steering: steering_pipelines(&castes),
+            spatial: SpatialGrid::new(neighbour_cell_size(&castes)),
+            castes,
+            obstacles: Vec::new(),
+            nests: Vec::new(),
+            food_sources: Vec::new(),
+            foraging: ForagingStats::default(),
+            bounds_radius,
+            tick: 0,
+            time: 0.0,
+            seed,
+            rng: rng::substream(seed, rng::WORLD_STREAM),
+            next_ant_id: 0,
+            next_entity_id: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn castes(&self) -> &CasteTable {
+        &self.castes
+    }
+
+    /// Replaces the caste profiles and rebuilds the steering pipelines from them.
+    pub fn set_castes(&mut self, castes: CasteTable) {
+        self.steering = steering_pipelines(&castes);
+        self.spatial = SpatialGrid::new(neighbour_cell_size(&castes));
+        self.castes = castes;
+    }
+
+    /// Replaces the steering pipeline of `caste`, e.g. to plug in custom behaviors.
+    pub fn set_steering(&mut self, caste: Caste, pipeline: SteeringPipeline) {
+        self.steering[caste.index()] = pipeline;
+    }
+
+    pub fn spatial(&self) -> &SpatialGrid {
+        &self.spatial
+    }
+
+    pub fn rebuild_spatial_index(&mut self) {
+        self.spatial.rebuild(self.ants.iter().map(|ant| ant.position));
+    }
+
+    /// Steering force of every ant, indexed like `ants`, with the threat it
+    /// senses, computed from the state at the start of the step. Read-only, so
+    /// large swarms are split across threads.
+    fn steering_forces(&self) -> Vec<(glm::Vec3, Option<glm::Vec3>)> {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        if threads <= 1 || self.ants.len() < PARALLEL_STEERING_MIN_ANTS {
+            return self.steering_forces_for(&self.ants);
+        }
+        let chunk = self.ants.len().div_ceil(threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> =
+                self.ants.chunks(chunk).map(|ants| scope.spawn(move || self.steering_forces_for(ants))).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+
+    fn steering_forces_for(&self, ants: &[Ant]) -> Vec<(glm::Vec3, Option<glm::Vec3>)> {
+        let mut neighbours: Vec<&Ant> = Vec::with_capacity(MAX_NEIGHBOURS + 1);
+        ants.iter()
+            .map(|ant| {
+                let profile = self.castes.get(ant.caste);
+                neighbours.clear();
+                // The ant itself is always its own nearest hit.
+                for (index, _) in self.spatial.k_nearest(&ant.position, MAX_NEIGHBOURS + 1, profile.sensing_radius) {
+                    if self.ants[index].id != ant.id {
+                        neighbours.push(&self.ants[index]);
+                    }
+                }
+                neighbours.truncate(MAX_NEIGHBOURS);
+                let ctx = SteeringContext {
+                    ant,
+                    profile,
+                    neighbours: &neighbours,
+                    pheromones: &self.pheromones,
+                    obstacles: &self.obstacles,
+                    bounds_radius: self.bounds_radius,
+                };
+                // Neighbours come closest first.
+                let threat = neighbours
+                    .iter()
+                    .find(|other| ant.nest.is_some() && other.nest.is_some() && other.nest != ant.nest)
+                    .map(|other| other.position);
+                (self.steering[ant.caste.index()].steer(&ctx), threat)
+            })
+            .collect()
+    }
+
+    pub fn spawn_ant(&mut self, caste: Caste, position: glm::Vec3, velocity: glm::Vec3) -> u32 {
+        let id = self.next_ant_id;
+        self.next_ant_id += 1;
+        self.ants.push(Ant::new(id, caste, position, velocity, rng::ant_stream(self.seed, id)));
+        id
+    }
+
+    /// Scatters `count` ants of `caste` uniformly inside the world bounds.
+    pub fn populate(&mut self, caste: Caste, count: usize) {
+        let speed = 0.5 * self.castes.get(caste).max_speed;
+        for _ in 0..count {
+            let position = random_in_sphere(&mut self.rng, self.bounds_radius);
+            let velocity = random_on_sphere(&mut self.rng, speed);
+            self.spawn_ant(caste, position, velocity);
+        }
+    }
+
+    /// Spawns `count` ants of `caste` inside the nest `nest_id` and makes it their home.
+    pub fn populate_nest(&mut self, nest_id: u32, caste: Caste, count: usize) {
+        let Some(nest) = self.nests.iter().find(|nest| nest.id == nest_id) else {
+            return;
+        };
+        let (center, radius) = (nest.position, nest.radius);
+        let speed = 0.5 * self.castes.get(caste).max_speed;
+        for _ in 0..count {
+            let position = center + random_in_sphere(&mut self.rng, radius);
+            let velocity = random_on_sphere(&mut self.rng, speed);
+            self.spawn_ant(caste, position, velocity);
+            self.ants.last_mut().unwrap().nest = Some(nest_id);
+        }
+    }
+
+    pub fn add_nest(&mut self, position: glm::Vec3, radius: f32) -> u32 {
+        let id = self.next_entity_id();
+        self.nests.push(Nest { id, position, radius, stock: 0.0, food_collected: 0.0 });
+        id
+    }
+
+    pub fn add_food_source(&mut self, position: glm::Vec3, radius: f32, capacity: f32, regeneration_rate: f32) -> u32 {
+        let id = self.next_entity_id();
+        self.food_sources.push(FoodSource { id, position, radius, stock: capacity, capacity, regeneration_rate });
+        id
+    }
+
+    /// Replaces all food sources with `count` new ones scattered in the outer
+    /// half of the world.
+    pub fn respawn_food_sources(&mut self, count: usize) {
+        self.food_sources.clear();
+        for _ in 0..count {
+            let distance = self.rng.random_range(0.5..0.9) * self.bounds_radius;
+            let position = random_on_sphere(&mut self.rng, distance);
+            self.add_food_source(position, DEFAULT_FOOD_RADIUS, DEFAULT_FOOD_CAPACITY, DEFAULT_FOOD_REGENERATION);
+        }
+    }
+
+    fn next_entity_id(&mut self) -> u32 {
+        let id = self.next_entity_id;
+        self.next_entity_id += 1;
+        id
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.rebuild_spatial_index();
+
+        let forces = self.steering_forces();
+
+        for (ant, (force, threat)) in self.ants.iter_mut().zip(forces) {
+            let profile = self.castes.get(ant.caste);
+            ant.threat = threat;
+            if ant.threat.is_some() {
+                self.pheromones.deposit(Channel::Alarm, &ant.position, ALARM_DEPOSIT_RATE * dt);
+            }
+
+            ant.age += dt;
+            ant.energy -= profile.energy_burn_rate * dt;
+            if ant.age >= profile.lifespan {
+                ant.energy = 0.0;
+            }
+
+            ant.wander_dir = glm::normalize(&(ant.wander_dir + random_on_sphere(&mut ant.rng, WANDER_JITTER * dt)));
+            flight::fly(ant, profile, force, dt);
+
+            // Bounce off the bounding sphere.
+            let dist = glm::length(&ant.position);
+            if dist > self.bounds_radius {
+                let normal = ant.position / dist;
+                bounce(ant, &(-normal));
+                ant.position = normal * self.bounds_radius;
+            }
+
+            // Push out of any obstacle the ant ended up inside.
+            for obstacle in &self.obstacles {
+                let offset = ant.position - obstacle.center;
+                let dist = glm::length(&offset);
+                if dist < obstacle.radius && dist > 1e-6 {
+                    bounce(ant, &(offset / dist));
+                    ant.position = obstacle.center + offset * (obstacle.radius / dist);
+                }
+            }
+
+            foraging::update(
+                ant,
+                profile,
+                &mut self.nests,
+                &mut self.food_sources,
+                &mut self.pheromones,
+                &mut self.foraging,
+                dt,
+            );
+        }
+        for food in &mut self.food_sources {
+            food.regenerate(dt);
+        }
+        self.foraging.end_tick(dt);
+        self.ants.retain(Ant::is_alive);
+        self.pheromones.update(dt);
+
+        self.tick += 1;
+        self.time += dt as f64;
+    }
+
+    /// Hash of the bit patterns of the whole simulation state, including the
+    /// position of every random stream. Equal hashes mean identical worlds.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.tick, self.time.to_bits(), self.seed).hash(&mut hasher);
+        (self.rng.get_word_pos(), self.next_ant_id, self.next_entity_id).hash(&mut hasher);
+        for ant in &self.ants {
+            (ant.id, ant.caste.index(), ant.nest, ant.state.name(), ant.rng.get_word_pos()).hash(&mut hasher);
+            hash_floats(&mut hasher, ant.position.iter().chain(&ant.velocity).chain(&ant.wander_dir));
+            hash_floats(&mut hasher, ant.orientation.coords.iter());
+            hash_floats(&mut hasher, &[ant.energy, ant.age, ant.bank, ant.carrying]);
+            if let ForageState::Recruiting { remaining } = ant.state {
+                hash_floats(&mut hasher, &[remaining]);
+            }
+            for point in [ant.target, ant.threat, ant.food_memory] {
+                point.is_some().hash(&mut hasher);
+                hash_floats(&mut hasher, point.iter().flatten());
+            }
+        }
+        for nest in &self.nests {
+            nest.id.hash(&mut hasher);
+            hash_floats(&mut hasher, nest.position.iter().chain(&[nest.radius, nest.stock, nest.food_collected]));
+        }
+        for food in &self.food_sources {
+            food.id.hash(&mut hasher);
+            hash_floats(&mut hasher, food.position.iter());
+            hash_floats(&mut hasher, &[food.radius, food.stock, food.capacity, food.regeneration_rate]);
+        }
+        hash_floats(&mut hasher, &[self.foraging.delivered, self.foraging.recent_income]);
+        self.foraging.trips.hash(&mut hasher);
+        // Chunks live in a hash map, so visit them in a fixed order.
+        for channel in Channel::ALL {
+            let mut chunks: Vec<_> = self.pheromones.channel(channel).chunks().collect();
+            chunks.sort_by_key(|(key, _)| (key.x, key.y, key.z));
+            for (key, values) in chunks {
+                (key.x, key.y, key.z).hash(&mut hasher);
+                hash_floats(&mut hasher, values);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// Builds two worlds with `build`, steps both `steps` times and checks that
+/// they stay bit-identical. Halfway through, the second world is replaced by
+/// a copy restored from its snapshot. Returns the final state hash.
+pub fn verify_determinism<F: Fn() -> World>(build: F, steps: u64) -> Result<u64, String> {
+    let (mut a, mut b) = (build(), build());
+    for step in 0..steps {
+        if step == steps / 2 {
+            b = snapshot::load(&snapshot::save(&b))?;
+        }
+        if a.state_hash() != b.state_hash() {
+            return Err(format!("Runs with seed {} diverged at tick {}", a.seed, a.tick));
+        }
+        a.step(FIXED_DT);
+        b.step(FIXED_DT);
+    }
+    let hash = a.state_hash();
+    if hash != b.state_hash() {
+        return Err(format!("Runs with seed {} diverged at tick {}", a.seed, a.tick));
+    }
+    Ok(hash)
+}
+
+/// Reflects the velocity of `ant` off a surface with unit `normal` if it is
+/// flying into it, and turns the ant to face its new velocity so that `fly`
+/// does not keep turning it back into the surface. The wander direction is
+/// reflected too, or `Wander` would steer straight back.
+fn bounce(ant: &mut Ant, normal: &glm::Vec3) {
+    let vn = glm::dot(&ant.velocity, normal);
+    if vn < 0.0 {
+        ant.velocity -= 2.0 * vn * normal;
+        ant.face_velocity();
+    }
+    let wn = glm::dot(&ant.wander_dir, normal);
+    if wn < 0.0 {
+        ant.wander_dir -= 2.0 * wn * normal;
+    }
+}
+
+fn hash_floats<'a, H: Hasher, I: IntoIterator<Item = &'a f32>>(hasher: &mut H, values: I) {
+    for value in values {
+        value.to_bits().hash(hasher);
+    }
+}
+
+/// Cell size of the neighbour index: small enough that a k-nearest query in a
+/// dense swarm only touches a couple of shells of cells.
+fn neighbour_cell_size(castes: &CasteTable) -> f32 {
+    Caste::ALL.iter().map(|caste| castes.get(*caste).sensing_radius).fold(0.1, f32::max) / 2.0
+}
+
+fn steering_pipelines(castes: &CasteTable) -> [SteeringPipeline; Caste::ALL.len()] {
+    Caste::ALL.map(|caste| SteeringPipeline::from_weights(&castes.get(caste).steering))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::scenario::Scenario;
+
+    const SEED: u64 = 42;
+    const STEPS: u64 = 60;
+
+    fn build() -> World {
+        Scenario::default().build(CasteTable::default(), SEED)
+    }
+
+    #[test]
+    fn same_seed_gives_identical_worlds() {
+        let (mut a, mut b) = (build(), build());
+        for _ in 0..STEPS {
+            a.step(FIXED_DT);
+            b.step(FIXED_DT);
+        }
+        assert_eq!(a.tick, STEPS);
+        assert_eq!(a.state_hash(), b.state_hash());
+        assert_eq!(snapshot::save(&a), snapshot::save(&b));
+    }
+
+    #[test]
+    fn different_seeds_give_different_worlds() {
+        let (mut a, mut b) = (build(), Scenario::default().build(CasteTable::default(), SEED + 1));
+        for _ in 0..STEPS {
+            a.step(FIXED_DT);
+            b.step(FIXED_DT);
+        }
+        assert_ne!(a.state_hash(), b.state_hash());
+    }
+
+    #[test]
+    fn snapshot_restore_keeps_the_run_identical() {
+        assert!(verify_determinism(build, STEPS).is_ok());
+    }
+}
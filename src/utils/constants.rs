@@ -1 +1 @@
-pub const PI : f32 = 3.141592653589793;
+pub const PI : f32 = std::f32::consts::PI;
@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+
+use nalgebra_glm as glm;
+
+/// Corner `i` of the unit cube sits at `(i & 1, (i >> 1) & 1, (i >> 2) & 1)`.
+const CORNERS: usize = 8;
+
+/// The twelve cube edges as pairs of corners.
+const EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7), // along x
+    (0, 2), (1, 3), (4, 6), (5, 7), // along y
+    (0, 4), (1, 5), (2, 6), (3, 7), // along z
+];
+
+/// Triangles of each of the 256 inside/outside corner configurations, as
+/// triples of edge indices. Bit `i` of the configuration is set when corner
+/// `i` is inside, i.e. at or above the level.
+type CaseTable = Vec<Vec<[u8; 3]>>;
+
+fn corner(i: usize) -> glm::Vec3 {
+    glm::vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32)
+}
+
+/// Emits the triangles of the surface where the trilinear field with values
+/// `values` at the cube corners crosses `level`, in cube-local coordinates.
+/// Triangles wind counter-clockwise seen from the outside (lower) side.
+pub fn march_cell(values: &[f32; CORNERS], level: f32, mut emit: impl FnMut([glm::Vec3; 3])) {
+    let case = (0..CORNERS).filter(|&i| values[i] >= level).fold(0, |case, i| case | 1 << i);
+    let triangles = &case_table()[case];
+    if triangles.is_empty() {
+        return;
+    }
+    let vertex = |edge: u8| {
+        let (a, b) = EDGES[edge as usize];
+        let t = ((level - values[a]) / (values[b] - values[a])).clamp(0.0, 1.0);
+        glm::lerp(&corner(a), &corner(b), t)
+    };
+    for triangle in triangles {
+        emit(triangle.map(vertex));
+    }
+}
+
+fn case_table() -> &'static CaseTable {
+    static TABLE: OnceLock<CaseTable> = OnceLock::new();
+    TABLE.get_or_init(build_case_table)
+}
+
+/// Derives the triangulation of every case instead of spelling out the
+/// classic table. On each face the crossed edges are joined by segments that
+/// keep inside corners apart, which neighbouring cubes agree on, so surfaces
+/// have no holes. The segments of a cube close into loops that are fanned
+/// into triangles.
+fn build_case_table() -> CaseTable {
+    let edge_between = |a: usize, b: usize| EDGES.iter().position(|&e| e == (a.min(b), a.max(b))).unwrap();
+
+    // Corners of each face in counter-clockwise order seen from outside.
+    let mut faces = Vec::new();
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        for side in 0..2 {
+            let mut face = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(a, b)| side << axis | a << u | b << v);
+            let normal = corner(1 << axis) * if side == 1 { 1.0 } else { -1.0 };
+            let turn = glm::cross(&(corner(face[1]) - corner(face[0])), &(corner(face[2]) - corner(face[1])));
+            if glm::dot(&turn, &normal) < 0.0 {
+                face.reverse();
+            }
+            faces.push(face);
+        }
+    }
+
+    (0..1usize << CORNERS)
+        .map(|case| {
+            let inside = |corner: usize| case & 1 << corner != 0;
+            // next[e] is the edge the surface boundary continues to after edge e.
+            let mut next = [usize::MAX; EDGES.len()];
+            for face in &faces {
+                for i in 0..4 {
+                    // Each run of inside corners gets a segment from the edge
+                    // that leaves it to the edge that enters it.
+                    if inside(face[i]) && !inside(face[(i + 1) % 4]) {
+                        let mut before = i;
+                        while inside(face[before]) {
+                            before = (before + 3) % 4;
+                        }
+                        next[edge_between(face[i], face[(i + 1) % 4])] =
+                            edge_between(face[before], face[(before + 1) % 4]);
+                    }
+                }
+            }
+
+            let mut triangles = Vec::new();
+            let mut visited = [false; EDGES.len()];
+            for first in 0..EDGES.len() {
+                if next[first] == usize::MAX || visited[first] {
+                    continue;
+                }
+                let mut polygon = Vec::new();
+                let mut edge = first;
+                while !visited[edge] {
+                    visited[edge] = true;
+                    polygon.push(edge as u8);
+                    edge = next[edge];
+                }
+                // A loop may cross one face twice. Fan it from a vertex whose
+                // triangles don't lie flat in that face, where the neighbouring
+                // cube would emit the same triangle.
+                let flat = |triangle: [u8; 3]| {
+                    faces.iter().any(|face| {
+                        triangle.iter().all(|&e| face.contains(&EDGES[e as usize].0) && face.contains(&EDGES[e as usize].1))
+                    })
+                };
+                let polygon = &polygon;
+                let fan = |start: usize| {
+                    let vertex = move |i: usize| polygon[(start + i) % polygon.len()];
+                    (1..polygon.len() - 1).map(move |i| [vertex(0), vertex(i + 1), vertex(i)])
+                };
+                let start = (0..polygon.len()).find(|&start| !fan(start).any(flat)).unwrap_or(0);
+                triangles.extend(fan(start));
+            }
+            triangles
+        })
+        .collect()
+}
@@ -1,2 +1,2 @@
 pub mod constants;
-pub mod vec_utils;
+pub mod marching_cubes;